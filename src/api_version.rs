@@ -0,0 +1,79 @@
+//! The Vulkan API version the app can rely on: the one requested in the config, lowered to what
+//! vulkano and the device support.
+//!
+//! NOTE: vulkano 0.11 always creates the instance with API version 1.0, so for now that's what
+//! it always comes down to, whatever the config requests. It's only logged and reported
+//! (e.g. in the benchmark report); nothing is gated on it. For the same reason the loader's
+//! version (`vkEnumerateInstanceVersion`) isn't queried: every loader supports 1.0.
+use vulkano::instance::Version;
+
+/// NOTE: vulkano 0.11 always creates the instance with API version 1.0 (there's no way to pass
+/// another one), so that's also the limit for the device.
+pub const INSTANCE_VERSION: Version = Version { major: 1, minor: 0, patch: 0 };
+
+pub const VULKAN_1_0: Version = Version { major: 1, minor: 0, patch: 0 };
+
+/// `major.minor` or `major.minor.patch`, ignoring pre-release and build suffixes (like Cargo versions).
+pub fn parse(version: &str) -> Option<Version> {
+    let version = version.split(&['-', '+'][..]).next().unwrap_or("");
+    let parts = version.split('.')
+        .map(|part| part.parse::<u16>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match parts[..] {
+        [major, minor] => Some(Version { major, minor, patch: 0 }),
+        [major, minor, patch] => Some(Version { major, minor, patch }),
+        _ => None,
+    }
+}
+
+pub fn to_string(version: Version) -> String {
+    format!("{}.{}.{}", version.major, version.minor, version.patch)
+}
+
+/// The requested `major.minor`, lowered to what vulkano and the device support.
+/// Returns the first of them that lowered it, if any did.
+pub fn negotiate(requested: Version, device: Version) -> (Version, Option<&'static str>) {
+    let mut negotiated = (Version { patch: 0, ..requested }, None);
+    let limits = [("vulkano", INSTANCE_VERSION), ("the device", device)];
+    for &(limited_by, limit) in limits.iter() {
+        if (limit.major, limit.minor) < (negotiated.0.major, negotiated.0.minor) {
+            negotiated = (Version { patch: 0, ..limit }, Some(limited_by));
+        }
+    }
+    negotiated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(major: u16, minor: u16, patch: u16) -> Version {
+        Version { major, minor, patch }
+    }
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!(parse("1.1"), Some(version(1, 1, 0)));
+        assert_eq!(parse("1.2.131"), Some(version(1, 2, 131)));
+        assert_eq!(parse("0.1.0-alpha.1+build.5"), Some(version(0, 1, 0)));
+        assert_eq!(parse("1"), None);
+        assert_eq!(parse("1.2.3.4"), None);
+        assert_eq!(parse("1.x"), None);
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("1.70000"), None);
+    }
+
+    #[test]
+    fn negotiate_lowers_to_the_lowest_limit() {
+        // vulkano is the limit as long as it's 1.0
+        assert_eq!(negotiate(version(1, 1, 0), version(1, 1, 0)), (VULKAN_1_0, Some("vulkano")));
+        // the device is only checked after vulkano
+        assert_eq!(negotiate(version(1, 1, 0), version(1, 0, 65)), (VULKAN_1_0, Some("vulkano")));
+        assert_eq!(negotiate(version(2, 0, 0), version(1, 1, 0)), (VULKAN_1_0, Some("vulkano")));
+    }
+
+    #[test]
+    fn negotiate_ignores_patch_versions() {
+        assert_eq!(negotiate(version(1, 0, 3), version(1, 0, 61)), (VULKAN_1_0, None));
+    }
+}
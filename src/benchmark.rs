@@ -0,0 +1,129 @@
+//! `--benchmark [--frames N] [--warmup M] [--report <path>]` (or `[benchmark]` in the config):
+//! renders a fixed number of frames with a `FixedStepClock` (so every run renders the same frames),
+//! then writes the per-frame timings as JSON and exits.
+use std::fs::File;
+use std::io::{self, BufWriter};
+
+use serde_json;
+
+use clock;
+use frame_stats::FrameTimeSummary;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BenchmarkConfig {
+    pub frames: usize,
+    /// frames rendered before the measured ones (not in the report)
+    pub warmup: usize,
+    pub report_path: String,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self { frames: 1000, warmup: 100, report_path: "benchmark.json".to_string() }
+    }
+}
+
+/// Timings of one frame in milliseconds.
+#[derive(Serialize, Default)]
+pub struct FrameRecord {
+    /// all of `draw_frame`
+    pub cpu: f32,
+    /// GPU time read back in this frame, which belongs to an earlier frame (of the same swap chain image).
+    /// `None` if timestamp queries aren't supported or the result wasn't available yet.
+    pub gpu: Option<f32>,
+    /// waiting in `acquire_next_image`
+    pub acquire: f32,
+    /// submitting the command buffers and presenting
+    pub present: f32,
+}
+
+#[derive(Serialize)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub device_type: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub api_version: String,
+    /// what the app uses, see `api_version::negotiate`
+    pub negotiated_api_version: String,
+    /// decoded with `driver_version`
+    pub driver_version: String,
+    /// vendor-specific encoding
+    pub driver_version_raw: u32,
+}
+
+const VENDOR_NVIDIA: u32 = 0x10de;
+const VENDOR_INTEL: u32 = 0x8086;
+
+/// The driver version as the vendor writes it. NVIDIA and Intel's Windows driver have their own
+/// encodings, everyone else (e.g. Mesa) uses the one of Vulkan versions.
+pub fn driver_version(vendor_id: u32, raw: u32) -> String {
+    match vendor_id {
+        VENDOR_NVIDIA => format!("{}.{}.{}.{}", raw >> 22, (raw >> 14) & 0xff, (raw >> 6) & 0xff, raw & 0x3f),
+        VENDOR_INTEL if cfg!(windows) => format!("{}.{}", raw >> 14, raw & 0x3fff),
+        _ => format!("{}.{}.{}", raw >> 22, (raw >> 12) & 0x3ff, raw & 0xfff),
+    }
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    device: &'a DeviceInfo,
+    frames: usize,
+    warmup: usize,
+    time_step: f32,
+    cpu: Option<FrameTimeSummary>,
+    gpu: Option<FrameTimeSummary>,
+    frame_records: &'a [FrameRecord],
+}
+
+pub struct Benchmark {
+    pub config: BenchmarkConfig,
+    frame: usize,
+    records: Vec<FrameRecord>,
+}
+
+impl Benchmark {
+    pub fn new(config: BenchmarkConfig) -> Self {
+        let records = Vec::with_capacity(config.frames);
+        Self { config, frame: 0, records }
+    }
+
+    pub fn add_frame(&mut self, record: FrameRecord) {
+        if self.frame >= self.config.warmup {
+            self.records.push(record);
+        }
+        self.frame += 1;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.records.len() >= self.config.frames
+    }
+
+    pub fn write_report(&self, device: &DeviceInfo) -> io::Result<()> {
+        let report = Report {
+            device,
+            frames: self.records.len(),
+            warmup: self.config.warmup,
+            time_step: clock::DEFAULT_STEP,
+            cpu: FrameTimeSummary::new(self.records.iter().map(|record| record.cpu).collect()),
+            gpu: FrameTimeSummary::new(self.records.iter().filter_map(|record| record.gpu).collect()),
+            frame_records: &self.records,
+        };
+        let file = BufWriter::new(File::create(&self.config.report_path)?);
+        serde_json::to_writer_pretty(file, &report)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_driver_versions() {
+        assert_eq!(driver_version(VENDOR_NVIDIA, (430 << 22) | (26 << 14) | (1 << 6) | 2), "430.26.1.2");
+        // Mesa 19.1.3 (AMD)
+        assert_eq!(driver_version(0x1002, (19 << 22) | (1 << 12) | 3), "19.1.3");
+    }
+}
@@ -0,0 +1,154 @@
+//! The tutorial chapters: one binary per step, each a copy of the previous one plus what the step adds.
+
+/// A tutorial step: one of the other binaries in `src/bin`.
+pub struct Chapter {
+    /// binary name (the file name in `src/bin` without `.rs`)
+    pub name: &'static str,
+    pub title: &'static str,
+    pub url: &'static str,
+    /// what it adds to the previous chapter
+    pub adds: &'static str,
+}
+
+impl Chapter {
+    /// The number prefix of the binary name, e.g. `15` for `15_hello_triangle`.
+    pub fn number(&self) -> &'static str {
+        self.name.split('_').next().unwrap()
+    }
+}
+
+/// In tutorial order. There's no chapter 07 (image views are handled by Vulkano) and no 17
+/// (the vertex shader change is combined with 18, since it wouldn't compile alone).
+pub const CHAPTERS: &[Chapter] = &[
+    Chapter {
+        name: "00_base_code",
+        title: "Base code",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Setup/Base_code",
+        adds: "application structure and a winit window",
+    },
+    Chapter {
+        name: "01_instance_creation",
+        title: "Instance",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Setup/Instance",
+        adds: "Vulkan instance with the extensions winit needs",
+    },
+    Chapter {
+        name: "02_validation_layers",
+        title: "Validation layers",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Setup/Validation_layers",
+        adds: "validation layers and a debug callback (debug builds)",
+    },
+    Chapter {
+        name: "03_physical_device_selection",
+        title: "Physical devices and queue families",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Setup/Physical_devices_and_queue_families",
+        adds: "picking a GPU with a graphics queue family",
+    },
+    Chapter {
+        name: "04_logical_device",
+        title: "Logical device and queues",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Setup/Logical_device_and_queues",
+        adds: "logical device and graphics queue",
+    },
+    Chapter {
+        name: "05_window_surface",
+        title: "Window surface",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Presentation/Window_surface",
+        adds: "window surface and present queue",
+    },
+    Chapter {
+        name: "06_swap_chain_creation",
+        title: "Swap chain",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Presentation/Swap_chain",
+        adds: "swap chain (format, present mode and extent selection)",
+    },
+    Chapter {
+        name: "08_graphics_pipeline",
+        title: "Graphics pipeline basics",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Graphics_pipeline_basics",
+        adds: "empty create_graphics_pipeline",
+    },
+    Chapter {
+        name: "09_shader_modules",
+        title: "Shader modules",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Graphics_pipeline_basics/Shader_modules",
+        adds: "vertex and fragment shaders, compiled by vulkano_shaders",
+    },
+    Chapter {
+        name: "10_fixed_functions",
+        title: "Fixed functions",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Graphics_pipeline_basics/Fixed_functions",
+        adds: "viewport, rasterizer, multisampling and blending state",
+    },
+    Chapter {
+        name: "11_render_passes",
+        title: "Render passes",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Graphics_pipeline_basics/Render_passes",
+        adds: "render pass with a color attachment",
+    },
+    Chapter {
+        name: "12_graphics_pipeline_complete",
+        title: "Graphics pipeline conclusion",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Graphics_pipeline_basics/Conclusion",
+        adds: "the complete graphics pipeline",
+    },
+    Chapter {
+        name: "13_framebuffers",
+        title: "Framebuffers",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Drawing/Framebuffers",
+        adds: "a framebuffer per swap chain image",
+    },
+    Chapter {
+        name: "14_command_buffers",
+        title: "Command buffers",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Drawing/Command_buffers",
+        adds: "command buffers that draw the triangle",
+    },
+    Chapter {
+        name: "15_hello_triangle",
+        title: "Rendering and presentation",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Drawing/Rendering_and_presentation",
+        adds: "draw_frame: acquire, submit and present (the first triangle)",
+    },
+    Chapter {
+        name: "16_swap_chain_recreation",
+        title: "Swap chain recreation",
+        url: "https://vulkan-tutorial.com/Drawing_a_triangle/Swap_chain_recreation",
+        adds: "recreating the swap chain when the window is resized",
+    },
+    Chapter {
+        name: "18_vertex_buffer",
+        title: "Vertex buffers",
+        url: "https://vulkan-tutorial.com/Vertex_buffers/Vertex_buffer_creation",
+        adds: "vertex input description and a CpuAccessibleBuffer with the vertices",
+    },
+    Chapter {
+        name: "19_staging_buffer",
+        title: "Staging buffer",
+        url: "https://vulkan-tutorial.com/Vertex_buffers/Staging_buffer",
+        adds: "ImmutableBuffer (uploads via a staging buffer) instead of CpuAccessibleBuffer",
+    },
+    Chapter {
+        name: "20_index_buffer",
+        title: "Index buffer",
+        url: "https://vulkan-tutorial.com/Vertex_buffers/Index_buffer",
+        adds: "index buffer and indexed drawing of a rectangle",
+    },
+    Chapter {
+        name: "21_descriptor_layout_and_buffer",
+        title: "Descriptor layout and buffer",
+        url: "https://vulkan-tutorial.com/Uniform_buffers/Descriptor_layout_and_buffer",
+        adds: "uniform buffer with model, view and projection matrices",
+    },
+];
+
+/// By number (`9` or `09`), name (`15_hello_triangle`) or part of the name (`triangle`).
+pub fn find(query: &str) -> Option<&'static Chapter> {
+    if query.is_empty() {
+        return None;
+    }
+    let number = query.parse::<u32>().ok();
+    CHAPTERS.iter()
+        .find(|chapter| chapter.name == query || (number.is_some() && chapter.number().parse().ok() == number))
+        .or_else(|| CHAPTERS.iter().find(|chapter| chapter.name.contains(query)))
+}
@@ -0,0 +1,223 @@
+//! Lists and launches the tutorial chapters, so there's one entry point for all of them:
+//! ```text
+//! cargo build --bins
+//! cargo run --bin chapters -- list
+//! cargo run --bin chapters -- run 15
+//! cargo run --bin chapters -- run-all [--headless] [--seconds N]
+//! ```
+//! The chapters are the other binaries of this package, launched from the same target directory
+//! (so they need to be built first).
+//!
+//! `run-all` runs the chapters one after another, each until its window is closed. With `--headless`
+//! it's non-interactive instead: every chapter is stopped after `--seconds` (5 by default), its
+//! output goes to a log file and the result is a pass/fail report (exit code 1 if any chapter failed).
+//! A chapter fails if it couldn't be started or exited unsuccessfully before it was stopped
+//! (e.g. a panic or a Vulkan error).
+//! NOTE: the chapters are kept close to the tutorial, so they have no frame counter or hidden window
+//! of their own. That means the runner can't tell whether a chapter that's still running actually
+//! draws frames, and their windows still show up - on a machine without a display, run the whole
+//! runner under a virtual one, e.g. `xvfb-run cargo run --bin chapters -- run-all --headless`.
+mod chapters;
+
+use std::env;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::process::{self, Child, Command, ExitStatus};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chapters::{Chapter, CHAPTERS};
+
+const DEFAULT_SECONDS: u64 = 5;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const USAGE: &str = "usage: chapters list | run <chapter> | run-all [--headless] [--seconds N]";
+
+enum Outcome {
+    /// still running at the end of the time budget (and stopped then)
+    Running,
+    Exited(ExitStatus),
+    /// couldn't be started
+    Failed(io::Error),
+}
+
+impl Outcome {
+    fn passed(&self) -> bool {
+        match self {
+            Outcome::Running => true,
+            Outcome::Exited(status) => status.success(),
+            Outcome::Failed(_) => false,
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            Outcome::Running => "still running".to_string(),
+            Outcome::Exited(status) => status.to_string(),
+            Outcome::Failed(err) => format!("failed to start: {}", err),
+        }
+    }
+}
+
+/// Next to the runner's own executable.
+fn executable(chapter: &Chapter) -> io::Result<PathBuf> {
+    let runner = env::current_exe()?;
+    Ok(runner.with_file_name(format!("{}{}", chapter.name, env::consts::EXE_SUFFIX)))
+}
+
+fn spawn(chapter: &Chapter, log: Option<&File>) -> io::Result<Child> {
+    let path = executable(chapter)?;
+    if !path.exists() {
+        return Err(io::Error::new(io::ErrorKind::NotFound,
+            format!("{} not found (build the chapters with `cargo build --bins`)", path.display())));
+    }
+    let mut command = Command::new(path);
+    if let Some(log) = log {
+        command.stdout(log.try_clone()?).stderr(log.try_clone()?);
+    }
+    command.spawn()
+}
+
+fn log_path(chapter: &Chapter) -> PathBuf {
+    env::temp_dir().join(format!("vulkan-tutorial-{}.log", chapter.name))
+}
+
+/// Waits for the chapter to exit, killing it after `budget` (if any).
+fn wait(mut child: Child, budget: Option<Duration>) -> Outcome {
+    let budget = match budget {
+        Some(budget) => budget,
+        None => return child.wait().map(Outcome::Exited).unwrap_or_else(Outcome::Failed),
+    };
+
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Outcome::Exited(status),
+            Ok(None) if start.elapsed() >= budget => {
+                // NOTE: fails if it exited in the meantime, which doesn't matter here
+                let _ = child.kill();
+                let _ = child.wait();
+                return Outcome::Running;
+            },
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(err) => return Outcome::Failed(err),
+        }
+    }
+}
+
+fn list() {
+    for chapter in CHAPTERS {
+        println!("{:>2}  {}", chapter.number(), chapter.title);
+        println!("    {}", chapter.url);
+        println!("    adds: {}", chapter.adds);
+    }
+}
+
+fn run(query: &str) -> i32 {
+    let chapter = match chapters::find(query) {
+        Some(chapter) => chapter,
+        None => {
+            eprintln!("no chapter matches '{}' (see `chapters list`)", query);
+            return 2;
+        },
+    };
+    println!("{} - {}\n{}", chapter.name, chapter.title, chapter.url);
+    let outcome = match spawn(chapter, None) {
+        Ok(child) => wait(child, None),
+        Err(err) => Outcome::Failed(err),
+    };
+    if outcome.passed() {
+        0
+    } else {
+        eprintln!("{}: {}", chapter.name, outcome.description());
+        1
+    }
+}
+
+fn run_all(headless: bool, seconds: u64) -> i32 {
+    let budget = if headless { Some(Duration::from_secs(seconds)) } else { None };
+    let mut failed = 0;
+    for chapter in CHAPTERS {
+        if !headless {
+            println!("{} - {} (close the window to continue)", chapter.name, chapter.title);
+        }
+        let log = if headless { File::create(log_path(chapter)).ok() } else { None };
+        let outcome = match spawn(chapter, log.as_ref()) {
+            Ok(child) => wait(child, budget),
+            Err(err) => Outcome::Failed(err),
+        };
+
+        let result = if outcome.passed() { "pass" } else { "FAIL" };
+        println!("{}  {:<32} {}", result, chapter.name, outcome.description());
+        if !outcome.passed() {
+            failed += 1;
+            // the chapter ran, so there's output
+            if let (Some(_), Outcome::Exited(_)) = (&log, &outcome) {
+                println!("      output: {}", log_path(chapter).display());
+            }
+        }
+    }
+    println!("{} of {} chapters passed", CHAPTERS.len() - failed, CHAPTERS.len());
+    if failed > 0 { 1 } else { 0 }
+}
+
+/// The positive number after `option`, or `default` if the option isn't there.
+fn count_of(args: &[String], option: &str, default: u64) -> Result<u64, String> {
+    let i = match args.iter().position(|arg| arg == option) {
+        Some(i) => i,
+        None => return Ok(default),
+    };
+    match args.get(i + 1).map(|value| (value, value.parse())) {
+        Some((_, Ok(count))) if count > 0 => Ok(count),
+        Some((value, _)) => Err(format!("invalid value for {}: {}", option, value)),
+        None => Err(format!("missing value for {}", option)),
+    }
+}
+
+/// The first argument of `run-all` that isn't one of its options.
+fn unknown_option(args: &[String]) -> Option<String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => (),
+            "--seconds" => {
+                args.next();
+            },
+            _ => return Some(format!("unknown option: {}", arg)),
+        }
+    }
+    None
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let code = match args.first().map(String::as_str) {
+        Some("list") => {
+            list();
+            0
+        },
+        Some("run") => match args.get(1) {
+            Some(query) => run(query),
+            None => {
+                eprintln!("{}", USAGE);
+                2
+            },
+        },
+        Some("run-all") => {
+            let headless = args.iter().any(|arg| arg == "--headless");
+            match unknown_option(&args[1..]).map_or_else(|| count_of(&args, "--seconds", DEFAULT_SECONDS), Err) {
+                Ok(seconds) => run_all(headless, seconds),
+                Err(err) => {
+                    eprintln!("{}\n{}", err, USAGE);
+                    2
+                },
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        },
+    };
+    process::exit(code);
+}
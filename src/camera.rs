@@ -0,0 +1,217 @@
+//! Interactive camera, driven by the `InputState` collected in `main_loop`.
+//!
+//! Orbit mode: drag with the left mouse button to rotate around the target, scroll to zoom,
+//! drag with the right (or middle) mouse button to pan.
+//! Fly mode: the `Move*` actions (WASD and Q/E by default) to move (faster with shift),
+//! drag with the left mouse button to look around.
+//!
+//! Like the tutorial's fixed view, Z is up.
+//!
+//! The `CycleProjection` action switches between the projections of the `projection` module.
+//! There's no depth buffer, so the reverse-Z variants only differ in the depth values they produce.
+use std::f32::consts::PI;
+use std::time::Instant;
+
+use cgmath::{InnerSpace, Matrix4, Point3, Rad, Deg, Vector3};
+use winit::MouseButton;
+
+use input::{Action, Bindings, InputState};
+use projection;
+
+/// Radians per pixel of mouse movement
+const ROTATION_SPEED: f32 = 0.005;
+/// Fraction of the distance per scroll line
+const ZOOM_SPEED: f32 = 0.1;
+/// Units per second
+const FLY_SPEED: f32 = 2.0;
+/// Factor while shift is held
+const FAST_FLY_FACTOR: f32 = 4.0;
+const MAX_PITCH: f32 = PI / 2.0 - 0.01;
+/// Seconds; longer gaps between frames (e.g. when rendering on demand) don't make the camera jump
+const MAX_FRAME_TIME: f32 = 0.1;
+const FOVY: Deg<f32> = Deg(45.0);
+const NEAR: f32 = 0.1;
+const FAR: f32 = 100.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    Orbit,
+    Fly,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProjectionKind {
+    Perspective,
+    PerspectiveReverseZ,
+    PerspectiveInfinite,
+    PerspectiveInfiniteReverseZ,
+    /// as big as the perspective view at the orbit distance
+    Orthographic,
+}
+
+pub struct Camera {
+    pub mode: CameraMode,
+    pub projection: ProjectionKind,
+    aspect: f32,
+
+    // orbit
+    target: Point3<f32>,
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+
+    // fly
+    position: Point3<f32>,
+    fly_yaw: f32,
+    fly_pitch: f32,
+
+    last_update: Instant,
+}
+
+impl Camera {
+    /// Orbits the origin from the tutorial's viewpoint (2, 2, 2).
+    pub fn new(dimensions: [u32; 2]) -> Self {
+        let mut camera = Self {
+            mode: CameraMode::Orbit,
+            projection: ProjectionKind::Perspective,
+            aspect: 1.0,
+            target: Point3::new(0.0, 0.0, 0.0),
+            distance: 0.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            position: Point3::new(0.0, 0.0, 0.0),
+            fly_yaw: 0.0,
+            fly_pitch: 0.0,
+            last_update: Instant::now(),
+        };
+        camera.set_dimensions(dimensions);
+        camera.reset();
+        camera
+    }
+
+    pub fn reset(&mut self) {
+        self.target = Point3::new(0.0, 0.0, 0.0);
+        self.distance = 12.0f32.sqrt();
+        self.yaw = PI / 4.0;
+        self.pitch = (2.0 / self.distance).asin();
+        self.start_flying_from_orbit();
+    }
+
+    pub fn set_dimensions(&mut self, dimensions: [u32; 2]) {
+        self.aspect = dimensions[0] as f32 / dimensions[1] as f32;
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            CameraMode::Orbit => {
+                self.start_flying_from_orbit();
+                CameraMode::Fly
+            },
+            CameraMode::Fly => CameraMode::Orbit,
+        };
+    }
+
+    pub fn cycle_projection(&mut self) {
+        self.projection = match self.projection {
+            ProjectionKind::Perspective => ProjectionKind::PerspectiveReverseZ,
+            ProjectionKind::PerspectiveReverseZ => ProjectionKind::PerspectiveInfinite,
+            ProjectionKind::PerspectiveInfinite => ProjectionKind::PerspectiveInfiniteReverseZ,
+            ProjectionKind::PerspectiveInfiniteReverseZ => ProjectionKind::Orthographic,
+            ProjectionKind::Orthographic => ProjectionKind::Perspective,
+        };
+    }
+
+    /// Fly from the orbit camera's position in the same direction.
+    fn start_flying_from_orbit(&mut self) {
+        self.position = self.orbit_eye();
+        self.fly_yaw = self.yaw + PI;
+        self.fly_pitch = -self.pitch;
+    }
+
+    fn orbit_eye(&self) -> Point3<f32> {
+        self.target + direction(self.yaw, self.pitch) * self.distance
+    }
+
+    /// Call once per frame. Movement is in real time, independent of the animation clock.
+    pub fn update(&mut self, input: &InputState, bindings: &Bindings) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update);
+        self.last_update = now;
+        let seconds = (elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1_000_000_000.0)
+            .min(MAX_FRAME_TIME);
+
+        let (dx, dy) = input.mouse_delta();
+        let rotating = input.is_button_down(MouseButton::Left);
+        let panning = input.is_button_down(MouseButton::Right) || input.is_button_down(MouseButton::Middle);
+
+        match self.mode {
+            CameraMode::Orbit => {
+                if rotating {
+                    self.yaw -= dx * ROTATION_SPEED;
+                    self.pitch = (self.pitch + dy * ROTATION_SPEED).max(-MAX_PITCH).min(MAX_PITCH);
+                } else if panning {
+                    let forward = -direction(self.yaw, self.pitch);
+                    let right = forward.cross(Vector3::unit_z()).normalize();
+                    let up = right.cross(forward);
+                    let scale = self.distance * 0.002;
+                    self.target += (-right * dx + up * dy) * scale;
+                }
+                self.distance = (self.distance * (1.0 - input.scroll() * ZOOM_SPEED)).max(0.1);
+            },
+            CameraMode::Fly => {
+                if rotating {
+                    self.fly_yaw -= dx * ROTATION_SPEED;
+                    self.fly_pitch = (self.fly_pitch - dy * ROTATION_SPEED).max(-MAX_PITCH).min(MAX_PITCH);
+                }
+
+                let forward = direction(self.fly_yaw, self.fly_pitch);
+                let right = forward.cross(Vector3::unit_z()).normalize();
+                let axis = |positive: Action, negative: Action| {
+                    bindings.is_active(positive, input) as i32 as f32 - bindings.is_active(negative, input) as i32 as f32
+                };
+                let movement = forward * axis(Action::MoveForward, Action::MoveBackward)
+                    + right * axis(Action::MoveRight, Action::MoveLeft)
+                    + Vector3::unit_z() * axis(Action::MoveUp, Action::MoveDown);
+                let speed = if input.modifiers().shift { FLY_SPEED * FAST_FLY_FACTOR } else { FLY_SPEED };
+                self.position += movement * speed * seconds;
+            },
+        }
+    }
+
+    /// Moving by itself while keys are held, i.e. it needs new frames without new events.
+    pub fn is_moving(&self, input: &InputState, bindings: &Bindings) -> bool {
+        self.mode == CameraMode::Fly && [
+            Action::MoveForward, Action::MoveBackward, Action::MoveLeft,
+            Action::MoveRight, Action::MoveDown, Action::MoveUp,
+        ].iter().any(|&action| bindings.is_active(action, input))
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        match self.mode {
+            CameraMode::Orbit => projection::look_at(self.orbit_eye(), self.target, Vector3::unit_z()),
+            CameraMode::Fly => projection::look_to(self.position, direction(self.fly_yaw, self.fly_pitch),
+                Vector3::unit_z()),
+        }
+    }
+
+    pub fn projection(&self) -> Matrix4<f32> {
+        let fovy = Rad::from(FOVY);
+        match self.projection {
+            ProjectionKind::Perspective => projection::perspective(fovy, self.aspect, NEAR, FAR),
+            ProjectionKind::PerspectiveReverseZ => projection::perspective_reverse_z(fovy, self.aspect, NEAR, FAR),
+            ProjectionKind::PerspectiveInfinite => projection::perspective_infinite(fovy, self.aspect, NEAR),
+            ProjectionKind::PerspectiveInfiniteReverseZ =>
+                projection::perspective_infinite_reverse_z(fovy, self.aspect, NEAR),
+            ProjectionKind::Orthographic => {
+                let half_height = self.distance * (fovy.0 / 2.0).tan();
+                let half_width = half_height * self.aspect;
+                projection::orthographic(-half_width, half_width, -half_height, half_height, NEAR, FAR)
+            },
+        }
+    }
+}
+
+/// Unit vector for the angles (yaw around Z, starting at X; pitch towards Z).
+fn direction(yaw: f32, pitch: f32) -> Vector3<f32> {
+    Vector3::new(pitch.cos() * yaw.cos(), pitch.cos() * yaw.sin(), pitch.sin())
+}
@@ -0,0 +1,212 @@
+//! Time source for all animation, so runs can be made reproducible.
+//!
+//! A `Clock` says how much time passed since the last frame. `AnimationTime` accumulates it,
+//! and adds pause, single-stepping and speed controls on top.
+use std::time::Instant;
+
+use config::ClockSetting;
+
+/// Time step of `FixedStepClock::default()` and of `AnimationTime::step` (60 Hz).
+pub const DEFAULT_STEP: f32 = 1.0 / 60.0;
+
+pub trait Clock {
+    /// Seconds since the last call (the first call is relative to the creation of the clock).
+    fn delta(&mut self) -> f32;
+
+    /// Whether time passes without any interaction.
+    fn advances(&self) -> bool {
+        true
+    }
+}
+
+/// The actual time between frames.
+pub struct RealTimeClock {
+    last: Instant,
+}
+
+impl Default for RealTimeClock {
+    fn default() -> Self {
+        Self { last: Instant::now() }
+    }
+}
+
+impl Clock for RealTimeClock {
+    fn delta(&mut self) -> f32 {
+        let now = Instant::now();
+        let duration = now.duration_since(self.last);
+        self.last = now;
+        duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.0
+    }
+}
+
+/// The same time step in each frame, independent of the frame rate.
+pub struct FixedStepClock {
+    pub step: f32,
+}
+
+impl Default for FixedStepClock {
+    fn default() -> Self {
+        Self { step: DEFAULT_STEP }
+    }
+}
+
+impl Clock for FixedStepClock {
+    fn delta(&mut self) -> f32 {
+        self.step
+    }
+}
+
+/// Never advances by itself, only through `AnimationTime::step`.
+pub struct ManualClock;
+
+impl Clock for ManualClock {
+    fn delta(&mut self) -> f32 {
+        0.0
+    }
+
+    fn advances(&self) -> bool {
+        false
+    }
+}
+
+const MIN_SPEED: f32 = 1.0 / 16.0;
+const MAX_SPEED: f32 = 16.0;
+
+pub struct AnimationTime {
+    clock: Box<Clock>,
+    seconds: f32,
+    speed: f32,
+    paused: bool,
+}
+
+impl AnimationTime {
+    pub fn new(clock: Box<Clock>) -> Self {
+        Self { clock, seconds: 0.0, speed: 1.0, paused: false }
+    }
+
+    /// `frame.clock` in the config (`--fixed-step` or `--manual-clock`).
+    pub fn from_config(clock: ClockSetting) -> Self {
+        Self::new(match clock {
+            ClockSetting::RealTime => Box::new(RealTimeClock::default()),
+            ClockSetting::FixedStep => Box::new(FixedStepClock::default()),
+            ClockSetting::Manual => Box::new(ManualClock),
+        })
+    }
+
+    /// Call once per submitted frame, which was drawn at the `seconds` before the call.
+    pub fn tick(&mut self) {
+        let delta = self.clock.delta();
+        if !self.paused {
+            self.seconds += delta * self.speed;
+        }
+    }
+
+    /// Animation time of the frame to draw.
+    pub fn seconds(&self) -> f32 {
+        self.seconds
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Advances by one `DEFAULT_STEP` (scaled by the speed); also works while paused.
+    pub fn step(&mut self) {
+        self.seconds += DEFAULT_STEP * self.speed;
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).min(MAX_SPEED);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2.0).max(MIN_SPEED);
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// The animation time changes in every frame by itself.
+    pub fn is_animating(&self) -> bool {
+        !self.paused && self.clock.advances()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The animation times of `frames` submitted frames.
+    fn run(time: &mut AnimationTime, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|_| {
+                let seconds = time.seconds();
+                time.tick();
+                seconds
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fixed_step_is_exact_and_repeatable() {
+        // a step that's exact in binary, so the sums are too
+        let mut time = AnimationTime::new(Box::new(FixedStepClock { step: 0.25 }));
+        assert_eq!(run(&mut time, 5), [0.0, 0.25, 0.5, 0.75, 1.0]);
+
+        let mut first = AnimationTime::from_config(ClockSetting::FixedStep);
+        let mut second = AnimationTime::from_config(ClockSetting::FixedStep);
+        assert_eq!(run(&mut first, 100), run(&mut second, 100));
+    }
+
+    #[test]
+    fn manual_clock_only_steps() {
+        let mut time = AnimationTime::from_config(ClockSetting::Manual);
+        assert!(!time.is_animating());
+        assert_eq!(run(&mut time, 10), [0.0; 10]);
+
+        time.step();
+        time.step();
+        assert_eq!(time.seconds(), DEFAULT_STEP + DEFAULT_STEP);
+        assert_eq!(run(&mut time, 3), [DEFAULT_STEP * 2.0; 3]);
+    }
+
+    #[test]
+    fn no_time_passes_without_a_submitted_frame() {
+        // `tick` is only called after a frame was submitted, so a frame that failed (e.g. the swap
+        // chain was out of date) is redrawn at the same time and nothing is skipped
+        let mut time = AnimationTime::new(Box::new(FixedStepClock { step: 0.5 }));
+        time.tick();
+        assert_eq!(time.seconds(), 0.5);
+        assert_eq!(time.seconds(), 0.5);
+        time.tick();
+        assert_eq!(time.seconds(), 1.0);
+    }
+
+    #[test]
+    fn pause_and_speed() {
+        let mut time = AnimationTime::new(Box::new(FixedStepClock { step: 0.5 }));
+        time.toggle_pause();
+        time.tick();
+        assert_eq!(time.seconds(), 0.0);
+        assert!(!time.is_animating());
+
+        time.toggle_pause();
+        time.faster();
+        time.tick();
+        assert_eq!(time.seconds(), 1.0);
+
+        for _ in 0..10 {
+            time.faster();
+        }
+        assert_eq!(time.speed(), MAX_SPEED);
+        for _ in 0..20 {
+            time.slower();
+        }
+        assert_eq!(time.speed(), MIN_SPEED);
+    }
+}
@@ -0,0 +1,569 @@
+//! Window and renderer settings from an optional TOML file and the command line.
+//!
+//! `--config <path>` loads a file like
+//! ```toml
+//! api_version = "1.1"
+//! validation = true
+//! device = "nvidia"
+//! present_mode = "fifo"
+//! msaa_samples = 4
+//! pipeline = "double_sided"
+//!
+//! [application]
+//! name = "My Viewer"
+//! version = "2.3.0"
+//!
+//! [window]
+//! width = 1280
+//! height = 720
+//! title = "Vulkan"
+//!
+//! [scene]
+//! kind = "objects"
+//! count = 1000
+//!
+//! [frame]
+//! threads = 4
+//! max_fps = 60
+//! clock = "fixed_step"
+//!
+//! [benchmark]
+//! frames = 500
+//! ```
+//! Options on the command line override the file: `--width N`, `--height N`, `--title T`,
+//! `--validation`/`--no-validation`, `--device <index or name>`, `--present-mode <mode>`,
+//! `--msaa N`, `--pipeline <preset or path>`, `--headless`, `--shader-dir <path>`, `--bindings <path>`,
+//! `--api-version <major.minor>`, `--app-name <name>` and `--app-version <version>`;
+//! for the scene `--quads [N]`, `--objects [N]` or `--instances [N]`;
+//! for rendering the frames `--record-per-frame`, `--threads N`, `--on-demand`, `--max-fps N`,
+//! `--fixed-step` or `--manual-clock`, and `--csv <path>`;
+//! `--benchmark [--frames N] [--warmup M] [--report <path>]` (see `benchmark`; the other three
+//! need `--benchmark` or a `[benchmark]` table). If an option is given twice, the last one wins.
+//! Unknown options are an error.
+//! `--print-config` prints the effective settings (in the file format) and exits.
+//!
+//! NOTE: any `api_version` above 1.0 is accepted, but lowered to 1.0: vulkano 0.11 always creates
+//! a Vulkan 1.0 instance. And `headless` only hides the window, so a window system (e.g. a virtual
+//! display like Xvfb) is still needed.
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::iter::Peekable;
+
+use toml;
+use vulkano::instance::{ApplicationInfo, Version};
+use vulkano::swapchain::PresentMode;
+
+use api_version;
+use benchmark::BenchmarkConfig;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// `major.minor`; lowered to what vulkano and the device support
+    /// (see `api_version::negotiate`). NOTE: that's always 1.0 with vulkano 0.11.
+    pub api_version: String,
+    /// defaults to on in debug builds
+    pub validation: bool,
+    /// index or (case-insensitive) part of the name of the physical device; the first suitable one if not set
+    pub device: Option<String>,
+    pub present_mode: PresentModeSetting,
+    /// samples per pixel (1 = no multisampling); lowered to what the device supports
+    pub msaa_samples: u32,
+    /// fixed-function state: one of `pipeline_description::PRESETS` or the path of a TOML file
+    /// (see `PipelineDescription::from_toml`)
+    pub pipeline: String,
+    /// doesn't show the window (a window system is still needed for the swap chain)
+    pub headless: bool,
+    // NOTE: tables need to come after the plain values for serializing to TOML
+    pub application: ApplicationConfig,
+    pub window: WindowConfig,
+    pub assets: AssetPaths,
+    pub scene: SceneConfig,
+    pub frame: FrameConfig,
+    /// renders a fixed number of frames, writes a report and exits
+    pub benchmark: Option<BenchmarkConfig>,
+    /// `--print-config` (only on the command line)
+    #[serde(skip)]
+    pub print_config: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            api_version: "1.0".to_string(),
+            validation: cfg!(debug_assertions),
+            device: None,
+            present_mode: PresentModeSetting::Auto,
+            msaa_samples: 1,
+            pipeline: "default".to_string(),
+            headless: false,
+            application: ApplicationConfig::default(),
+            window: WindowConfig::default(),
+            assets: AssetPaths::default(),
+            scene: SceneConfig::default(),
+            frame: FrameConfig::default(),
+            benchmark: None,
+            print_config: false,
+        }
+    }
+}
+
+/// What the instance reports to drivers and tools (e.g. capture tools). Defaults to this package.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ApplicationConfig {
+    pub name: String,
+    pub version: String,
+    pub engine_name: String,
+    pub engine_version: String,
+}
+
+impl Default for ApplicationConfig {
+    fn default() -> Self {
+        Self {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            engine_name: env!("CARGO_PKG_NAME").to_string(),
+            engine_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub title: String,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self { width: 800, height: 600, title: "Vulkan".to_string() }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AssetPaths {
+    /// GLSL sources, for checking the shader interface (the SPIR-V is built in at compile time)
+    pub shader_dir: String,
+    /// key bindings (see `input::Bindings::from_toml`); the defaults if not set or the file doesn't exist
+    pub bindings: Option<String>,
+}
+
+impl Default for AssetPaths {
+    fn default() -> Self {
+        Self {
+            shader_dir: concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders").to_string(),
+            bindings: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneKind {
+    /// the rectangle of the tutorial
+    SingleQuad,
+    /// per-object data in push constants (see `scene::DrawMode`)
+    Quads,
+    /// per-object data in one storage buffer
+    Objects,
+    Instances,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SceneConfig {
+    pub kind: SceneKind,
+    /// objects or instances; defaults to 9 quads, 256 objects or 10000 instances
+    pub count: Option<usize>,
+}
+
+impl Default for SceneConfig {
+    fn default() -> Self {
+        Self { kind: SceneKind::SingleQuad, count: None }
+    }
+}
+
+impl SceneConfig {
+    pub fn count(&self) -> usize {
+        self.count.unwrap_or(match self.kind {
+            SceneKind::SingleQuad => 1,
+            SceneKind::Quads => 9,
+            SceneKind::Objects => 256,
+            SceneKind::Instances => 10_000,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockSetting {
+    RealTime,
+    /// the same time step in every frame (see `clock::FixedStepClock`)
+    FixedStep,
+    /// only advances when stepping
+    Manual,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FrameConfig {
+    /// records a command buffer in every frame instead of reusing one per swap chain image
+    pub record_per_frame: bool,
+    /// records the draw calls into secondary command buffers on this many threads
+    /// (1 = directly into the primary command buffer)
+    pub threads: usize,
+    /// only renders when something changed
+    pub on_demand: bool,
+    /// frame rate cap when rendering continuously (0 = none)
+    pub max_fps: u32,
+    pub clock: ClockSetting,
+    /// all frame times are written to this CSV file at exit
+    pub csv: Option<String>,
+}
+
+impl Default for FrameConfig {
+    fn default() -> Self {
+        Self {
+            record_per_frame: false,
+            threads: 1,
+            on_demand: false,
+            max_fps: 0,
+            clock: ClockSetting::RealTime,
+            csv: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentModeSetting {
+    /// mailbox, immediate or fifo, whichever is supported first (like the tutorial)
+    Auto,
+    Fifo,
+    FifoRelaxed,
+    Mailbox,
+    Immediate,
+}
+
+impl PresentModeSetting {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(PresentModeSetting::Auto),
+            "fifo" => Some(PresentModeSetting::Fifo),
+            "fifo_relaxed" => Some(PresentModeSetting::FifoRelaxed),
+            "mailbox" => Some(PresentModeSetting::Mailbox),
+            "immediate" => Some(PresentModeSetting::Immediate),
+            _ => None,
+        }
+    }
+
+    /// `None` for `Auto`
+    pub fn present_mode(self) -> Option<PresentMode> {
+        match self {
+            PresentModeSetting::Auto => None,
+            PresentModeSetting::Fifo => Some(PresentMode::Fifo),
+            PresentModeSetting::FifoRelaxed => Some(PresentMode::Relaxed),
+            PresentModeSetting::Mailbox => Some(PresentMode::Mailbox),
+            PresentModeSetting::Immediate => Some(PresentMode::Immediate),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    /// option, value
+    InvalidValue(String, String),
+    MissingValue(String),
+    UnknownOption(String),
+    /// option, what it needs
+    Requires(String, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "failed to read config file: {}", err),
+            ConfigError::Toml(err) => write!(f, "failed to parse config file: {}", err),
+            ConfigError::InvalidValue(option, value) => write!(f, "invalid value for {}: {}", option, value),
+            ConfigError::MissingValue(option) => write!(f, "missing value for {}", option),
+            ConfigError::UnknownOption(option) => write!(f, "unknown option {}", option),
+            ConfigError::Requires(option, required) => write!(f, "{} requires {}", option, required),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_toml(source: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(source).map_err(ConfigError::Toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: &str) -> Result<Self, ConfigError> {
+        let source = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::from_toml(&source)
+    }
+
+    /// The file given with `--config` (or the defaults), overridden by the other options.
+    pub fn from_args() -> Result<Self, ConfigError> {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let mut config = match args.iter().rposition(|arg| arg == "--config") {
+            Some(i) => Self::load(value_of(&mut args[i + 1..].iter().peekable(), "--config")?)?,
+            None => Self::default(),
+        };
+        config.apply_args(&args)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// The versions need to be parseable, the counts at least 1.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let versions = [
+            ("api_version", &self.api_version),
+            ("application.version", &self.application.version),
+            ("application.engine_version", &self.application.engine_version),
+        ];
+        for &(option, version) in versions.iter() {
+            if api_version::parse(version).is_none() {
+                return Err(ConfigError::InvalidValue(option.to_string(), version.clone()));
+            }
+        }
+
+        let mut counts = vec![("frame.threads", self.frame.threads)];
+        if let Some(count) = self.scene.count {
+            counts.push(("scene.count", count));
+        }
+        if let Some(ref benchmark) = self.benchmark {
+            counts.push(("benchmark.frames", benchmark.frames));
+        }
+        for &(option, count) in counts.iter() {
+            if count == 0 {
+                return Err(ConfigError::InvalidValue(option.to_string(), count.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// `args` without the program name.
+    fn apply_args(&mut self, args: &[String]) -> Result<(), ConfigError> {
+        let mut args = args.iter().peekable();
+        // applied at the end, when it's known whether there is a benchmark
+        let mut benchmark_options = vec![];
+        while let Some(arg) = args.next() {
+            let option = arg.as_str();
+            match option {
+                // already loaded by `from_args`
+                "--config" => { value_of(&mut args, option)?; },
+                "--print-config" => self.print_config = true,
+                "--api-version" => self.api_version = value_of(&mut args, option)?.clone(),
+                "--app-name" => self.application.name = value_of(&mut args, option)?.clone(),
+                "--app-version" => self.application.version = value_of(&mut args, option)?.clone(),
+                "--width" => self.window.width = parse(option, value_of(&mut args, option)?)?,
+                "--height" => self.window.height = parse(option, value_of(&mut args, option)?)?,
+                "--title" => self.window.title = value_of(&mut args, option)?.clone(),
+                "--validation" => self.validation = true,
+                "--no-validation" => self.validation = false,
+                "--device" => self.device = Some(value_of(&mut args, option)?.clone()),
+                "--present-mode" => {
+                    let present_mode = value_of(&mut args, option)?;
+                    self.present_mode = PresentModeSetting::from_name(present_mode)
+                        .ok_or_else(|| ConfigError::InvalidValue(option.to_string(), present_mode.clone()))?;
+                },
+                "--msaa" => self.msaa_samples = parse(option, value_of(&mut args, option)?)?,
+                "--pipeline" => self.pipeline = value_of(&mut args, option)?.clone(),
+                "--headless" => self.headless = true,
+                "--shader-dir" => self.assets.shader_dir = value_of(&mut args, option)?.clone(),
+                "--bindings" => self.assets.bindings = Some(value_of(&mut args, option)?.clone()),
+
+                "--instances" | "--objects" | "--quads" => {
+                    let kind = match option {
+                        "--instances" => SceneKind::Instances,
+                        "--objects" => SceneKind::Objects,
+                        _ => SceneKind::Quads,
+                    };
+                    let count = match optional_value_of(&mut args) {
+                        Some(count) => Some(parse(option, count)? as usize),
+                        None => None,
+                    };
+                    self.scene = SceneConfig { kind, count };
+                },
+
+                "--record-per-frame" => self.frame.record_per_frame = true,
+                "--threads" => self.frame.threads = parse(option, value_of(&mut args, option)?)? as usize,
+                "--on-demand" => self.frame.on_demand = true,
+                "--max-fps" => self.frame.max_fps = parse(option, value_of(&mut args, option)?)?,
+                "--fixed-step" => self.frame.clock = ClockSetting::FixedStep,
+                "--manual-clock" => self.frame.clock = ClockSetting::Manual,
+                "--csv" => self.frame.csv = Some(value_of(&mut args, option)?.clone()),
+
+                "--benchmark" => if self.benchmark.is_none() {
+                    self.benchmark = Some(BenchmarkConfig::default());
+                },
+                "--frames" | "--warmup" | "--report" => {
+                    benchmark_options.push((option, value_of(&mut args, option)?));
+                },
+
+                _ => return Err(ConfigError::UnknownOption(arg.clone())),
+            }
+        }
+
+        if let Some(&(option, _)) = benchmark_options.first() {
+            let benchmark = self.benchmark.as_mut()
+                .ok_or_else(|| ConfigError::Requires(option.to_string(),
+                    "--benchmark (or a [benchmark] table)".to_string()))?;
+            for (option, value) in benchmark_options {
+                match option {
+                    "--frames" => benchmark.frames = parse(option, value)? as usize,
+                    "--warmup" => benchmark.warmup = parse(option, value)? as usize,
+                    _ => benchmark.report_path = value.clone(),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn requested_api_version(&self) -> Version {
+        api_version::parse(&self.api_version).expect("api_version not validated!")
+    }
+
+    pub fn application_info(&self) -> ApplicationInfo<'_> {
+        ApplicationInfo {
+            application_name: Some(self.application.name.as_str().into()),
+            application_version: api_version::parse(&self.application.version),
+            engine_name: Some(self.application.engine_name.as_str().into()),
+            engine_version: api_version::parse(&self.application.engine_version),
+        }
+    }
+
+    /// The effective settings, in the config file format, with notes on what they can't change.
+    pub fn to_toml(&self) -> String {
+        let mut notes = String::new();
+        if api_version::parse(&self.api_version) != Some(api_version::VULKAN_1_0) {
+            notes += "# NOTE: api_version is lowered to 1.0, vulkano 0.11 always creates a Vulkan 1.0 instance\n";
+        }
+        if self.headless {
+            notes += "# NOTE: headless only hides the window, a window system is still needed for the swap chain\n";
+        }
+        notes + &toml::to_string(self).expect("failed to serialize config!")
+    }
+}
+
+/// The argument after `option`, which can't be another option.
+fn value_of<'a, I>(args: &mut Peekable<I>, option: &str) -> Result<&'a String, ConfigError>
+    where I: Iterator<Item=&'a String>
+{
+    match optional_value_of(args) {
+        Some(value) => Ok(value),
+        None => Err(ConfigError::MissingValue(option.to_string())),
+    }
+}
+
+/// The next argument, unless it's another option.
+fn optional_value_of<'a, I>(args: &mut Peekable<I>) -> Option<&'a String>
+    where I: Iterator<Item=&'a String>
+{
+    if args.peek().map_or(true, |arg| arg.starts_with("--")) {
+        return None;
+    }
+    args.next()
+}
+
+fn parse(option: &str, value: &str) -> Result<u32, ConfigError> {
+    value.parse().map_err(|_| ConfigError::InvalidValue(option.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    fn with_args(source: &str, arguments: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::from_toml(source)?;
+        config.apply_args(&args(arguments))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn args_override_the_file() {
+        let source = "msaa_samples = 4\n[window]\nwidth = 1280\ntitle = \"File\"\n[frame]\nthreads = 2\n";
+        let config = with_args(source, "--width 640 --threads 8 --no-validation").unwrap();
+        assert_eq!(config.window.width, 640);
+        assert_eq!(config.frame.threads, 8);
+        assert!(!config.validation);
+        // not on the command line
+        assert_eq!(config.msaa_samples, 4);
+        assert_eq!(config.window.title, "File");
+
+        let config = with_args("[scene]\nkind = \"quads\"\ncount = 4\n", "--objects 100").unwrap();
+        assert_eq!(config.scene.kind, SceneKind::Objects);
+        assert_eq!(config.scene.count(), 100);
+        let config = with_args("", "--quads --csv times.csv").unwrap();
+        assert_eq!(config.scene.count(), 9);
+        assert_eq!(config.frame.csv, Some("times.csv".to_string()));
+    }
+
+    #[test]
+    fn benchmark_options() {
+        let config = with_args("", "--frames 10 --benchmark --report out.json").unwrap();
+        let benchmark = config.benchmark.unwrap();
+        assert_eq!((benchmark.frames, benchmark.warmup, benchmark.report_path.as_str()), (10, 100, "out.json"));
+
+        let config = with_args("[benchmark]\nwarmup = 5\n", "--frames 10").unwrap();
+        assert_eq!(config.benchmark.map(|benchmark| (benchmark.frames, benchmark.warmup)), Some((10, 5)));
+
+        match with_args("", "--frames 10") {
+            Err(ConfigError::Requires(ref option, _)) if option == "--frames" => (),
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn invalid_args() {
+        match with_args("", "--msaa 4 --bogus") {
+            Err(ConfigError::UnknownOption(ref option)) if option == "--bogus" => (),
+            result => panic!("{:?}", result),
+        }
+        // the value can't be another option
+        match with_args("", "--frames --benchmark") {
+            Err(ConfigError::MissingValue(ref option)) if option == "--frames" => (),
+            result => panic!("{:?}", result),
+        }
+        match with_args("", "--width") {
+            Err(ConfigError::MissingValue(ref option)) if option == "--width" => (),
+            result => panic!("{:?}", result),
+        }
+        match with_args("", "--width wide") {
+            Err(ConfigError::InvalidValue(ref option, ref value)) if option == "--width" && value == "wide" => (),
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let invalid = [
+            ("", "--threads 0", "frame.threads"),
+            ("", "--instances 0", "scene.count"),
+            ("", "--benchmark --frames 0", "benchmark.frames"),
+            ("", "--api-version 1", "api_version"),
+            ("[application]\nversion = \"one\"\n", "", "application.version"),
+        ];
+        for &(source, arguments, invalid_option) in invalid.iter() {
+            match with_args(source, arguments) {
+                Err(ConfigError::InvalidValue(ref option, _)) if option == invalid_option => (),
+                result => panic!("{} {}: {:?}", source, arguments, result),
+            }
+        }
+    }
+}
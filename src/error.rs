@@ -0,0 +1,110 @@
+//! Errors while setting up (or rebuilding) the Vulkan objects and rendering, with the stage that failed.
+use std::fmt;
+use std::io;
+
+use vulkano::OomError;
+use vulkano::command_buffer::CommandBufferExecError;
+use vulkano::device::DeviceCreationError;
+use vulkano::framebuffer::{FramebufferCreationError, RenderPassCreationError};
+use vulkano::image::ImageCreationError;
+use vulkano::instance::InstanceCreationError;
+use vulkano::memory::DeviceMemoryAllocError;
+use vulkano::pipeline::GraphicsPipelineCreationError;
+use vulkano::swapchain::{AcquireError, CapabilitiesError, SwapchainCreationError};
+use vulkano::sync::FlushError;
+use vulkano_win::CreationError;
+
+use config::ConfigError;
+use input::BindingsError;
+use pipeline_description::PipelineDescriptionError;
+use recovery;
+use reflection::ReflectionError;
+
+/// Exit code if no GPU has the required queues, extensions and surface support.
+pub const EXIT_NO_SUITABLE_GPU: i32 = 2;
+/// Exit code for all other errors.
+pub const EXIT_FAILURE: i32 = 1;
+
+#[derive(Debug)]
+pub enum Error {
+    Config(ConfigError),
+    /// the path of the key bindings file and why it couldn't be loaded
+    Bindings(String, BindingsError),
+    Instance(InstanceCreationError),
+    Surface(CreationError),
+    SurfaceCapabilities(CapabilitiesError),
+    NoSuitableGpu,
+    Device(DeviceCreationError),
+    Swapchain(SwapchainCreationError),
+    RenderPass(RenderPassCreationError),
+    Framebuffer(FramebufferCreationError),
+    Image(ImageCreationError),
+    Shader(OomError),
+    /// the shaders couldn't be compiled or reflected to check them against the Rust types
+    ShaderInterface(ReflectionError),
+    PipelineDescription(PipelineDescriptionError),
+    Pipeline(GraphicsPipelineCreationError),
+    Memory(DeviceMemoryAllocError),
+    /// vulkano's message; it has separate error types for adding the resources and building the set
+    DescriptorSet(String),
+    /// vulkano's message; it has an error type per command
+    CommandBuffer(String),
+    Upload(FlushError),
+    Acquire(AcquireError),
+    Execute(CommandBufferExecError),
+    Present(FlushError),
+    /// still lost after `recovery::MAX_ATTEMPTS` attempts to rebuild it
+    DeviceLost,
+    SurfaceLost,
+    /// the path and why it couldn't be written
+    BenchmarkReport(String, io::Error),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::NoSuitableGpu => EXIT_NO_SUITABLE_GPU,
+            _ => EXIT_FAILURE,
+        }
+    }
+
+    pub fn descriptor_set<E: fmt::Display>(err: E) -> Self {
+        Error::DescriptorSet(err.to_string())
+    }
+
+    pub fn command_buffer<E: fmt::Display>(err: E) -> Self {
+        Error::CommandBuffer(err.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Config(err) => write!(f, "{}", err),
+            Error::Bindings(path, err) => write!(f, "failed to load key bindings from {}: {}", path, err),
+            Error::Instance(err) => write!(f, "failed to create Vulkan instance: {}", err),
+            Error::Surface(err) => write!(f, "failed to create window surface: {}", err),
+            Error::SurfaceCapabilities(err) => write!(f, "failed to get surface capabilities: {}", err),
+            Error::NoSuitableGpu => write!(f, "failed to find a suitable GPU"),
+            Error::Device(err) => write!(f, "failed to create logical device: {}", err),
+            Error::Swapchain(err) => write!(f, "failed to create swap chain: {}", err),
+            Error::RenderPass(err) => write!(f, "failed to create render pass: {}", err),
+            Error::Framebuffer(err) => write!(f, "failed to create framebuffer: {}", err),
+            Error::Image(err) => write!(f, "failed to create image: {}", err),
+            Error::Shader(err) => write!(f, "failed to create shader module: {}", err),
+            Error::ShaderInterface(err) => write!(f, "failed to check shader interface: {}", err),
+            Error::PipelineDescription(err) => write!(f, "{}", err),
+            Error::Pipeline(err) => write!(f, "failed to create graphics pipeline: {}", err),
+            Error::Memory(err) => write!(f, "failed to allocate memory: {}", err),
+            Error::DescriptorSet(msg) => write!(f, "failed to create descriptor set: {}", msg),
+            Error::CommandBuffer(msg) => write!(f, "failed to record command buffer: {}", msg),
+            Error::Upload(err) => write!(f, "failed to upload data: {}", err),
+            Error::Acquire(err) => write!(f, "failed to acquire swap chain image: {}", err),
+            Error::Execute(err) => write!(f, "failed to submit command buffer: {}", err),
+            Error::Present(err) => write!(f, "failed to submit or present frame: {}", err),
+            Error::DeviceLost => write!(f, "device lost, gave up after {} recovery attempts", recovery::MAX_ATTEMPTS),
+            Error::SurfaceLost => write!(f, "surface lost, gave up after {} recovery attempts", recovery::MAX_ATTEMPTS),
+            Error::BenchmarkReport(path, err) => write!(f, "failed to write benchmark report {}: {}", path, err),
+        }
+    }
+}
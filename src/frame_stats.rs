@@ -0,0 +1,225 @@
+//! CPU-side timings of the frames: totals to compare the command buffer recording modes,
+//! and frame times over a sliding window for the window title.
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+/// Number of frames the min/avg/max/99th percentile are computed over.
+const WINDOW: usize = 240;
+
+#[derive(Debug, Default)]
+pub struct FrameStats {
+    frames: u32,
+    frame_time: Duration,
+    recordings: u32,
+    recording_time: Duration,
+}
+
+impl FrameStats {
+    /// Time spent in `draw_frame`.
+    pub fn add_frame(&mut self, duration: Duration) {
+        self.frames += 1;
+        self.frame_time += duration;
+    }
+
+    /// Time spent recording command buffers (a single one, or all of them when prebuilding).
+    pub fn add_recording(&mut self, duration: Duration) {
+        self.recordings += 1;
+        self.recording_time += duration;
+    }
+
+    pub fn average_frame_time(&self) -> Duration {
+        if self.frames == 0 { Duration::default() } else { self.frame_time / self.frames }
+    }
+
+    /// Recording time amortized over all frames.
+    pub fn recording_time_per_frame(&self) -> Duration {
+        if self.frames == 0 { Duration::default() } else { self.recording_time / self.frames }
+    }
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} frames, {} us per frame, {} recording(s) taking {} us per frame",
+            self.frames, micros(self.average_frame_time()), self.recordings,
+            micros(self.recording_time_per_frame()))
+    }
+}
+
+/// Time between consecutive frames (i.e. including event handling and waiting for the swap chain).
+pub struct FrameTimes {
+    last_frame: Option<Instant>,
+    /// in milliseconds
+    window: VecDeque<f32>,
+    /// all frame times in milliseconds, only kept if they should be written to a CSV file
+    history: Option<Vec<f32>>,
+}
+
+/// All values in milliseconds (except `fps`).
+#[derive(Debug, Serialize)]
+pub struct FrameTimeSummary {
+    pub fps: f32,
+    pub min: f32,
+    pub avg: f32,
+    pub max: f32,
+    pub p99: f32,
+}
+
+impl FrameTimes {
+    pub fn new(keep_history: bool) -> Self {
+        Self {
+            last_frame: None,
+            window: VecDeque::with_capacity(WINDOW),
+            history: if keep_history { Some(Vec::new()) } else { None },
+        }
+    }
+
+    /// Call once per frame.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame {
+            let elapsed = now.duration_since(last_frame);
+            self.push(elapsed.as_secs() as f32 * 1000.0 + elapsed.subsec_nanos() as f32 / 1_000_000.0);
+        }
+        self.last_frame = Some(now);
+    }
+
+    fn push(&mut self, milliseconds: f32) {
+        if self.window.len() == WINDOW {
+            self.window.pop_front();
+        }
+        self.window.push_back(milliseconds);
+        if let Some(ref mut history) = self.history {
+            history.push(milliseconds);
+        }
+    }
+
+    pub fn summary(&self) -> Option<FrameTimeSummary> {
+        FrameTimeSummary::new(self.window.iter().cloned().collect())
+    }
+
+    /// Does nothing if the history wasn't kept.
+    pub fn write_csv(&self, path: &str) -> io::Result<()> {
+        let history = match self.history {
+            Some(ref history) => history,
+            None => return Ok(()),
+        };
+        write_history(history, BufWriter::new(File::create(path)?))
+    }
+}
+
+fn write_history<W: Write>(history: &[f32], mut out: W) -> io::Result<()> {
+    writeln!(out, "frame,frame_time_ms")?;
+    for (i, milliseconds) in history.iter().enumerate() {
+        writeln!(out, "{},{}", i, milliseconds)?;
+    }
+    Ok(())
+}
+
+impl FrameTimeSummary {
+    /// `None` if there are no frame times.
+    pub fn new(mut frame_times: Vec<f32>) -> Option<Self> {
+        if frame_times.is_empty() {
+            return None;
+        }
+        frame_times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let avg = frame_times.iter().sum::<f32>() / frame_times.len() as f32;
+        let p99_index = ((frame_times.len() as f32 * 0.99).ceil() as usize).max(1) - 1;
+        Some(Self {
+            // frames faster than the timer resolution
+            fps: if avg > 0.0 { 1000.0 / avg } else { 0.0 },
+            min: frame_times[0],
+            avg,
+            max: frame_times[frame_times.len() - 1],
+            p99: frame_times[p99_index],
+        })
+    }
+}
+
+impl fmt::Display for FrameTimeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.0} FPS, {:.2} ms (min {:.2}, max {:.2}, p99 {:.2})",
+            self.fps, self.avg, self.min, self.max, self.p99)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_times(milliseconds: &[f32]) -> FrameTimes {
+        let mut frame_times = FrameTimes::new(true);
+        for &milliseconds in milliseconds {
+            frame_times.push(milliseconds);
+        }
+        frame_times
+    }
+
+    #[test]
+    fn summary() {
+        let summary = frame_times(&[4.0, 1.0, 2.0, 5.0]).summary().unwrap();
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.max, 5.0);
+        assert_eq!(summary.avg, 3.0);
+        assert_eq!(summary.p99, 5.0);
+        assert!((summary.fps - 1000.0 / 3.0).abs() < 1e-3);
+
+        // the 99th of 100 sorted values
+        let summary = FrameTimeSummary::new((1..101).rev().map(|i| i as f32).collect()).unwrap();
+        assert_eq!(summary.p99, 99.0);
+        assert_eq!(summary.avg, 50.5);
+    }
+
+    #[test]
+    fn summary_of_few_frames() {
+        assert!(FrameTimes::new(false).summary().is_none());
+        assert!(FrameTimeSummary::new(vec![]).is_none());
+
+        let summary = FrameTimeSummary::new(vec![8.0]).unwrap();
+        assert_eq!((summary.min, summary.avg, summary.max, summary.p99), (8.0, 8.0, 8.0, 8.0));
+        assert_eq!(summary.fps, 125.0);
+
+        let summary = FrameTimeSummary::new(vec![0.0, 0.0]).unwrap();
+        assert_eq!(summary.fps, 0.0);
+    }
+
+    #[test]
+    fn summary_over_the_last_frames() {
+        let mut milliseconds = vec![100.0; 10];
+        milliseconds.extend(vec![1.0; WINDOW]);
+        let summary = frame_times(&milliseconds).summary().unwrap();
+        assert_eq!(summary.max, 1.0);
+    }
+
+    #[test]
+    fn csv() {
+        let mut out = vec![];
+        write_history(&frame_times(&[16.5, 17.0, 0.25]).history.unwrap(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "frame,frame_time_ms\n0,16.5\n1,17\n2,0.25\n");
+
+        let mut out = vec![];
+        write_history(&[], &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "frame,frame_time_ms\n");
+    }
+
+    #[test]
+    fn frame_stats_totals() {
+        let mut stats = FrameStats::default();
+        assert_eq!(stats.average_frame_time(), Duration::default());
+        assert_eq!(stats.recording_time_per_frame(), Duration::default());
+
+        stats.add_frame(Duration::from_millis(3));
+        stats.add_frame(Duration::from_millis(5));
+        stats.add_recording(Duration::from_millis(2));
+        assert_eq!(stats.average_frame_time(), Duration::from_millis(4));
+        assert_eq!(stats.recording_time_per_frame(), Duration::from_millis(1));
+        assert_eq!(stats.to_string(), "2 frames, 4000 us per frame, 1 recording(s) taking 1000 us per frame");
+    }
+}
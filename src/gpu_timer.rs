@@ -0,0 +1,215 @@
+//! Measures how long the GPU takes for a frame with timestamp queries.
+//!
+//! Vulkano 0.11 has no query commands on `AutoCommandBufferBuilder`, but its unsafe builder can
+//! record them. Each swap chain image has a pair of timestamp queries and two tiny secondary
+//! command buffers that the frame's command buffer executes around the render pass: the first
+//! resets the pair and writes the start timestamp, the second writes the end timestamp.
+//! The results are read back (without waiting) the next time the image is drawn, with the raw
+//! Vulkan functions of the device (`vk-sys`, which vulkano is built on).
+use std::collections::VecDeque;
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+
+use vk;
+use vulkano::buffer::BufferAccess;
+use vulkano::command_buffer::pool::standard::StandardCommandPoolAlloc;
+use vulkano::command_buffer::sys::{Flags, Kind, UnsafeCommandBuffer, UnsafeCommandBufferBuilder};
+use vulkano::command_buffer::{CommandBuffer, CommandBufferExecError, KindOcclusionQuery};
+use vulkano::device::{Device, DeviceOwned, Queue};
+use vulkano::image::{ImageAccess, ImageLayout};
+use vulkano::instance::PhysicalDevice;
+use vulkano::query::{QueryPipelineStatisticFlags, QueryType, UnsafeQueryPool};
+use vulkano::sync::{AccessCheckError, AccessFlagBits, GpuFuture, PipelineStages};
+use vulkano::VulkanObject;
+
+/// Number of frames the rolling average is computed over.
+const WINDOW: usize = 60;
+
+/// Secondary command buffer with query commands only, to be executed outside of a render pass.
+/// Doesn't access any buffers or images, so there's nothing to lock or check.
+pub struct QueryCommands {
+    inner: UnsafeCommandBuffer<StandardCommandPoolAlloc>,
+    /// keeps the pool alive while the command buffer can still be executed
+    _query_pool: Arc<UnsafeQueryPool>,
+}
+
+unsafe impl DeviceOwned for QueryCommands {
+    fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+}
+
+unsafe impl CommandBuffer for QueryCommands {
+    type PoolAlloc = StandardCommandPoolAlloc;
+
+    fn inner(&self) -> &UnsafeCommandBuffer<StandardCommandPoolAlloc> {
+        &self.inner
+    }
+
+    fn lock_submit(&self, _: &GpuFuture, _: &Queue) -> Result<(), CommandBufferExecError> {
+        Ok(())
+    }
+
+    unsafe fn unlock(&self) {}
+
+    fn check_buffer_access(&self, _: &BufferAccess, _: bool, _: &Queue)
+        -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError>
+    {
+        Err(AccessCheckError::Unknown)
+    }
+
+    fn check_image_access(&self, _: &ImageAccess, _: ImageLayout, _: bool, _: &Queue)
+        -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError>
+    {
+        Err(AccessCheckError::Unknown)
+    }
+}
+
+struct Slot {
+    begin: Arc<QueryCommands>,
+    end: Arc<QueryCommands>,
+    /// submitted, but the results haven't been read yet
+    pending: bool,
+}
+
+pub struct GpuTimer {
+    device: Arc<Device>,
+    query_pool: Arc<UnsafeQueryPool>,
+    slots: Vec<Slot>,
+    /// nanoseconds per timestamp tick
+    timestamp_period: f32,
+    /// the bits of the timestamps that are valid on the queue (the others are undefined)
+    timestamp_mask: u64,
+    samples: VecDeque<f32>,
+    /// the sample read in the last `begin_frame`, until taken
+    latest: Option<f32>,
+}
+
+impl GpuTimer {
+    /// Returns `None` if the device doesn't support timestamps on the queue.
+    pub fn new(physical_device: PhysicalDevice, device: &Arc<Device>, queue: &Arc<Queue>, image_count: usize) -> Option<Self> {
+        let limits = physical_device.limits();
+        if limits.timestamp_compute_and_graphics() == 0 {
+            return None;
+        }
+        let timestamp_mask = match timestamp_valid_bits(physical_device, queue.family().id()) {
+            0 => return None,
+            64 => !0,
+            bits => (1 << bits) - 1,
+        };
+
+        let query_pool = Arc::new(UnsafeQueryPool::new(device.clone(), QueryType::Timestamp,
+            (image_count * 2) as u32).ok()?);
+        let command_pool = Device::standard_command_pool(device, queue.family());
+        // writes the timestamp of `query`, after resetting the pair starting at it
+        let record = |query: u32, reset: bool, stages: PipelineStages| unsafe {
+            let kind = Kind::secondary(KindOcclusionQuery::Forbidden, QueryPipelineStatisticFlags::none());
+            // NOTE: the prebuilt command buffers of all images are executable at the same time
+            let mut builder = UnsafeCommandBufferBuilder::new(&command_pool, kind, Flags::SimultaneousUse).ok()?;
+            if reset {
+                builder.reset_query_pool(query_pool.queries_range(query, 2).unwrap());
+            }
+            builder.write_timestamp(query_pool.query(query).unwrap(), stages);
+            Some(Arc::new(QueryCommands { inner: builder.build().ok()?, _query_pool: query_pool.clone() }))
+        };
+
+        let slots = (0..image_count as u32)
+            .map(|i| Some(Slot {
+                begin: record(i * 2, true, PipelineStages { top_of_pipe: true, ..PipelineStages::none() })?,
+                end: record(i * 2 + 1, false, PipelineStages { bottom_of_pipe: true, ..PipelineStages::none() })?,
+                pending: false,
+            }))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            device: device.clone(),
+            query_pool,
+            slots,
+            timestamp_period: limits.timestamp_period(),
+            timestamp_mask,
+            samples: VecDeque::with_capacity(WINDOW),
+            latest: None,
+        })
+    }
+
+    /// To be executed right before the image's render pass.
+    pub fn begin_commands(&self, image_index: usize) -> Arc<QueryCommands> {
+        self.slots[image_index].begin.clone()
+    }
+
+    /// To be executed right after the image's render pass.
+    pub fn end_commands(&self, image_index: usize) -> Arc<QueryCommands> {
+        self.slots[image_index].end.clone()
+    }
+
+    /// Reads the results of the image's previous frame, if they are available. Otherwise the
+    /// frame's command buffer resets the queries and that sample is lost.
+    pub fn begin_frame(&mut self, image_index: usize) {
+        if !self.slots[image_index].pending {
+            return;
+        }
+
+        let mut timestamps = [0u64; 2];
+        let result = unsafe {
+            self.device.pointers().GetQueryPoolResults(
+                self.device.internal_object(),
+                self.query_pool.internal_object(),
+                (image_index * 2) as u32,
+                2,
+                mem::size_of_val(&timestamps),
+                timestamps.as_mut_ptr() as *mut _,
+                mem::size_of::<u64>() as u64,
+                vk::QUERY_RESULT_64_BIT,
+            )
+        };
+        self.slots[image_index].pending = false;
+        if result != vk::SUCCESS {
+            return;
+        }
+
+        let ticks = (timestamps[1] & self.timestamp_mask).wrapping_sub(timestamps[0] & self.timestamp_mask)
+            & self.timestamp_mask;
+        let milliseconds = ticks as f32 * self.timestamp_period / 1_000_000.0;
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(milliseconds);
+        self.latest = Some(milliseconds);
+    }
+
+    /// Must be called after the frame's command buffer was submitted successfully.
+    pub fn frame_submitted(&mut self, image_index: usize) {
+        self.slots[image_index].pending = true;
+    }
+
+    /// The GPU time (in milliseconds) read back in the last `begin_frame`, if not taken already.
+    /// It belongs to the previous frame that used the same swap chain image.
+    pub fn take_latest(&mut self) -> Option<f32> {
+        self.latest.take()
+    }
+
+    /// Average GPU time of the last frames in milliseconds.
+    pub fn average(&self) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<f32>() / self.samples.len() as f32)
+    }
+}
+
+/// `timestampValidBits` of the queue family; vulkano's `QueueFamily` doesn't have it.
+fn timestamp_valid_bits(physical_device: PhysicalDevice, queue_family_id: u32) -> u32 {
+    let vk = physical_device.instance().pointers();
+    unsafe {
+        let mut count = 0;
+        vk.GetPhysicalDeviceQueueFamilyProperties(physical_device.internal_object(), &mut count, ptr::null_mut());
+        let mut properties = Vec::with_capacity(count as usize);
+        vk.GetPhysicalDeviceQueueFamilyProperties(physical_device.internal_object(), &mut count,
+            properties.as_mut_ptr());
+        properties.set_len(count as usize);
+        properties.get(queue_family_id as usize)
+            .map(|properties: &vk::QueueFamilyProperties| properties.timestampValidBits)
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,400 @@
+//! Input state tracked from winit events, and a table binding actions to keys.
+//!
+//! `main_loop` feeds all window events into `InputState` and then dispatches the actions whose
+//! keys were pressed in this frame. Continuous actions (like camera movement) query
+//! `Bindings::is_active` instead.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+
+use toml;
+use winit::{ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+use error::Error;
+use logging;
+
+#[derive(Default)]
+pub struct InputState {
+    keys_down: HashSet<VirtualKeyCode>,
+    keys_pressed: HashSet<VirtualKeyCode>,
+    buttons_down: HashSet<MouseButton>,
+    cursor_position: Option<(f64, f64)>,
+    mouse_delta: (f64, f64),
+    /// in lines
+    scroll: f32,
+    modifiers: ModifiersState,
+}
+
+impl InputState {
+    /// Forgets everything that only applies to a single frame. Call before polling the events.
+    pub fn begin_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll = 0.0;
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode, modifiers, .. }, .. } => {
+                self.modifiers = modifiers;
+                let key = match virtual_keycode {
+                    Some(key) => key,
+                    None => return,
+                };
+                match state {
+                    // NOTE: key repeat sends more `Pressed` events without a `Released` in between
+                    ElementState::Pressed => if self.keys_down.insert(key) {
+                        self.keys_pressed.insert(key);
+                    },
+                    ElementState::Released => {
+                        self.keys_down.remove(&key);
+                    },
+                }
+            },
+            WindowEvent::MouseInput { state, button, modifiers, .. } => {
+                self.modifiers = modifiers;
+                match state {
+                    ElementState::Pressed => {
+                        self.buttons_down.insert(button);
+                    },
+                    ElementState::Released => {
+                        self.buttons_down.remove(&button);
+                    },
+                }
+            },
+            WindowEvent::CursorMoved { position, modifiers, .. } => {
+                self.modifiers = modifiers;
+                if let Some((x, y)) = self.cursor_position {
+                    self.mouse_delta.0 += position.x - x;
+                    self.mouse_delta.1 += position.y - y;
+                }
+                self.cursor_position = Some((position.x, position.y));
+            },
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseWheel { delta, modifiers, .. } => {
+                self.modifiers = modifiers;
+                self.scroll += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    // roughly one line
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+                };
+            },
+            // keys released while the window isn't focused would otherwise stay down
+            WindowEvent::Focused(false) => {
+                self.keys_down.clear();
+                self.buttons_down.clear();
+            },
+            _ => (),
+        }
+    }
+
+    pub fn is_key_down(&self, key: VirtualKeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn was_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    /// Cursor movement in this frame, in logical pixels.
+    pub fn mouse_delta(&self) -> (f32, f32) {
+        (self.mouse_delta.0 as f32, self.mouse_delta.1 as f32)
+    }
+
+    /// Scrolling in this frame, in lines.
+    pub fn scroll(&self) -> f32 {
+        self.scroll
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    ToggleWireframe,
+    ToggleVertexColor,
+    CycleColorLevels,
+    ToggleRecordingMode,
+    ToggleRecordingThreads,
+    Pause,
+    Step,
+    Faster,
+    Slower,
+    ToggleCameraMode,
+    CycleProjection,
+    ResetCamera,
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveDown,
+    MoveUp,
+}
+
+/// Names of the actions in the config file, with their default keys.
+const ACTIONS: &[(&str, Action, &[VirtualKeyCode])] = &[
+    ("toggle_wireframe", Action::ToggleWireframe, &[VirtualKeyCode::F]),
+    ("toggle_vertex_color", Action::ToggleVertexColor, &[VirtualKeyCode::C]),
+    ("cycle_color_levels", Action::CycleColorLevels, &[VirtualKeyCode::L]),
+    ("toggle_recording_mode", Action::ToggleRecordingMode, &[VirtualKeyCode::R]),
+    ("toggle_recording_threads", Action::ToggleRecordingThreads, &[VirtualKeyCode::T]),
+    ("pause", Action::Pause, &[VirtualKeyCode::P]),
+    ("step", Action::Step, &[VirtualKeyCode::N]),
+    ("faster", Action::Faster, &[VirtualKeyCode::Add, VirtualKeyCode::Equals]),
+    ("slower", Action::Slower, &[VirtualKeyCode::Subtract, VirtualKeyCode::Minus]),
+    ("toggle_camera_mode", Action::ToggleCameraMode, &[VirtualKeyCode::V]),
+    ("cycle_projection", Action::CycleProjection, &[VirtualKeyCode::O]),
+    ("reset_camera", Action::ResetCamera, &[VirtualKeyCode::Home]),
+    ("move_forward", Action::MoveForward, &[VirtualKeyCode::W]),
+    ("move_backward", Action::MoveBackward, &[VirtualKeyCode::S]),
+    ("move_left", Action::MoveLeft, &[VirtualKeyCode::A]),
+    ("move_right", Action::MoveRight, &[VirtualKeyCode::D]),
+    ("move_down", Action::MoveDown, &[VirtualKeyCode::Q]),
+    ("move_up", Action::MoveUp, &[VirtualKeyCode::E]),
+];
+
+/// Keys that can be used in the config file, by their `VirtualKeyCode` name.
+const KEYS: &[VirtualKeyCode] = &[
+    VirtualKeyCode::A, VirtualKeyCode::B, VirtualKeyCode::C, VirtualKeyCode::D, VirtualKeyCode::E,
+    VirtualKeyCode::F, VirtualKeyCode::G, VirtualKeyCode::H, VirtualKeyCode::I, VirtualKeyCode::J,
+    VirtualKeyCode::K, VirtualKeyCode::L, VirtualKeyCode::M, VirtualKeyCode::N, VirtualKeyCode::O,
+    VirtualKeyCode::P, VirtualKeyCode::Q, VirtualKeyCode::R, VirtualKeyCode::S, VirtualKeyCode::T,
+    VirtualKeyCode::U, VirtualKeyCode::V, VirtualKeyCode::W, VirtualKeyCode::X, VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Key0, VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3, VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5, VirtualKeyCode::Key6, VirtualKeyCode::Key7, VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+    VirtualKeyCode::F1, VirtualKeyCode::F2, VirtualKeyCode::F3, VirtualKeyCode::F4, VirtualKeyCode::F5,
+    VirtualKeyCode::F6, VirtualKeyCode::F7, VirtualKeyCode::F8, VirtualKeyCode::F9, VirtualKeyCode::F10,
+    VirtualKeyCode::F11, VirtualKeyCode::F12,
+    VirtualKeyCode::Escape, VirtualKeyCode::Space, VirtualKeyCode::Return, VirtualKeyCode::Tab,
+    VirtualKeyCode::Back, VirtualKeyCode::Insert, VirtualKeyCode::Delete, VirtualKeyCode::Home,
+    VirtualKeyCode::End, VirtualKeyCode::PageUp, VirtualKeyCode::PageDown,
+    VirtualKeyCode::Left, VirtualKeyCode::Right, VirtualKeyCode::Up, VirtualKeyCode::Down,
+    VirtualKeyCode::Add, VirtualKeyCode::Subtract, VirtualKeyCode::Equals, VirtualKeyCode::Minus,
+    VirtualKeyCode::Comma, VirtualKeyCode::Period,
+    VirtualKeyCode::LShift, VirtualKeyCode::RShift, VirtualKeyCode::LControl, VirtualKeyCode::RControl,
+    VirtualKeyCode::LAlt, VirtualKeyCode::RAlt,
+];
+
+fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    KEYS.iter().cloned().find(|key| format!("{:?}", key) == name)
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    UnknownAction(String),
+    UnknownKey(String),
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BindingsError::Io(err) => write!(f, "{}", err),
+            BindingsError::Toml(err) => write!(f, "{}", err),
+            BindingsError::UnknownAction(name) => write!(f, "unknown action {}", name),
+            BindingsError::UnknownKey(name) => write!(f, "unknown key {}", name),
+        }
+    }
+}
+
+/// Keys bound to each action.
+pub struct Bindings {
+    keys: HashMap<Action, Vec<VirtualKeyCode>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            keys: ACTIONS.iter()
+                .map(|&(_, action, keys)| (action, keys.to_vec()))
+                .collect(),
+        }
+    }
+}
+
+impl Bindings {
+    /// Overrides the default bindings, e.g.
+    /// ```toml
+    /// toggle_wireframe = ["F", "F2"]
+    /// move_forward = ["W", "Up"]
+    /// ```
+    pub fn from_toml(source: &str) -> Result<Self, BindingsError> {
+        let table: HashMap<String, Vec<String>> = toml::from_str(source).map_err(BindingsError::Toml)?;
+
+        let mut bindings = Self::default();
+        for (action_name, key_names) in table {
+            let action = ACTIONS.iter()
+                .find(|&&(name, _, _)| name == action_name)
+                .map(|&(_, action, _)| action)
+                .ok_or_else(|| BindingsError::UnknownAction(action_name.clone()))?;
+            let keys = key_names.iter()
+                .map(|name| key_from_name(name).ok_or_else(|| BindingsError::UnknownKey(name.clone())))
+                .collect::<Result<Vec<_>, _>>()?;
+            bindings.keys.insert(action, keys);
+        }
+        Ok(bindings)
+    }
+
+    pub fn load(path: &str) -> Result<Self, BindingsError> {
+        let source = fs::read_to_string(path).map_err(BindingsError::Io)?;
+        Self::from_toml(&source)
+    }
+
+    /// The bindings file (`assets.bindings` in the config), or the defaults if there's none or it
+    /// doesn't exist. A file that can't be read or parsed is an error.
+    pub fn load_or_default(path: Option<&str>) -> Result<Self, Error> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+        match Self::load(path) {
+            Err(BindingsError::Io(ref err)) if err.kind() == io::ErrorKind::NotFound => {
+                warn!(target: logging::INPUT, "bindings file {} not found, using the defaults", path);
+                Ok(Self::default())
+            },
+            result => result.map_err(|err| Error::Bindings(path.to_string(), err)),
+        }
+    }
+
+    /// One of the action's keys is held down.
+    pub fn is_active(&self, action: Action, input: &InputState) -> bool {
+        self.keys[&action].iter().any(|&key| input.is_key_down(key))
+    }
+
+    /// Actions for which one of the keys was pressed in this frame.
+    pub fn triggered<'a>(&'a self, input: &'a InputState) -> impl Iterator<Item=Action> + 'a {
+        ACTIONS.iter()
+            .map(|&(_, action, _)| action)
+            .filter(move |action| self.keys[action].iter().any(|&key| input.was_key_pressed(key)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::DeviceId;
+
+    use super::*;
+
+    fn key_event(state: ElementState, key: VirtualKeyCode) -> WindowEvent {
+        WindowEvent::KeyboardInput {
+            // NOTE: safe because it's never passed to winit
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput { scancode: 0, state, virtual_keycode: Some(key), modifiers: ModifiersState::default() },
+        }
+    }
+
+    fn button_event(state: ElementState, button: MouseButton) -> WindowEvent {
+        WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state,
+            button,
+            modifiers: ModifiersState::default(),
+        }
+    }
+
+    #[test]
+    fn key_state() {
+        let mut input = InputState::default();
+        input.begin_frame();
+        input.handle_event(&key_event(ElementState::Pressed, VirtualKeyCode::W));
+        assert!(input.is_key_down(VirtualKeyCode::W));
+        assert!(input.was_key_pressed(VirtualKeyCode::W));
+
+        // held down, with key repeat
+        input.begin_frame();
+        input.handle_event(&key_event(ElementState::Pressed, VirtualKeyCode::W));
+        assert!(input.is_key_down(VirtualKeyCode::W));
+        assert!(!input.was_key_pressed(VirtualKeyCode::W));
+
+        input.begin_frame();
+        input.handle_event(&key_event(ElementState::Released, VirtualKeyCode::W));
+        assert!(!input.is_key_down(VirtualKeyCode::W));
+        assert!(!input.was_key_pressed(VirtualKeyCode::W));
+
+        // pressed and released within a frame still counts as pressed
+        input.begin_frame();
+        input.handle_event(&key_event(ElementState::Pressed, VirtualKeyCode::F));
+        input.handle_event(&key_event(ElementState::Released, VirtualKeyCode::F));
+        assert!(!input.is_key_down(VirtualKeyCode::F));
+        assert!(input.was_key_pressed(VirtualKeyCode::F));
+    }
+
+    #[test]
+    fn button_state_and_focus_loss() {
+        let mut input = InputState::default();
+        input.handle_event(&button_event(ElementState::Pressed, MouseButton::Left));
+        input.handle_event(&key_event(ElementState::Pressed, VirtualKeyCode::A));
+        assert!(input.is_button_down(MouseButton::Left));
+        input.handle_event(&button_event(ElementState::Released, MouseButton::Left));
+        assert!(!input.is_button_down(MouseButton::Left));
+
+        input.handle_event(&button_event(ElementState::Pressed, MouseButton::Right));
+        input.handle_event(&WindowEvent::Focused(false));
+        assert!(!input.is_button_down(MouseButton::Right));
+        assert!(!input.is_key_down(VirtualKeyCode::A));
+    }
+
+    #[test]
+    fn default_bindings() {
+        let bindings = Bindings::default();
+        let mut input = InputState::default();
+        input.handle_event(&key_event(ElementState::Pressed, VirtualKeyCode::F));
+        input.handle_event(&key_event(ElementState::Pressed, VirtualKeyCode::Equals));
+        input.handle_event(&key_event(ElementState::Pressed, VirtualKeyCode::W));
+        let actions: Vec<Action> = bindings.triggered(&input).collect();
+        assert_eq!(actions, [Action::ToggleWireframe, Action::Faster, Action::MoveForward]);
+        assert!(bindings.is_active(Action::MoveForward, &input));
+        assert!(!bindings.is_active(Action::MoveBackward, &input));
+
+        // only pressed in this frame triggers, held down stays active
+        input.begin_frame();
+        assert_eq!(bindings.triggered(&input).count(), 0);
+        assert!(bindings.is_active(Action::MoveForward, &input));
+    }
+
+    #[test]
+    fn bindings_from_toml() {
+        let bindings = Bindings::from_toml("toggle_wireframe = [\"F2\", \"G\"]\nmove_forward = [\"Up\"]").unwrap();
+        assert_eq!(bindings.keys[&Action::ToggleWireframe], [VirtualKeyCode::F2, VirtualKeyCode::G]);
+        assert_eq!(bindings.keys[&Action::MoveForward], [VirtualKeyCode::Up]);
+        // the others keep their defaults
+        assert_eq!(bindings.keys[&Action::MoveBackward], [VirtualKeyCode::S]);
+
+        let mut input = InputState::default();
+        input.handle_event(&key_event(ElementState::Pressed, VirtualKeyCode::F));
+        assert_eq!(bindings.triggered(&input).count(), 0);
+        input.handle_event(&key_event(ElementState::Pressed, VirtualKeyCode::G));
+        assert_eq!(bindings.triggered(&input).collect::<Vec<_>>(), [Action::ToggleWireframe]);
+    }
+
+    #[test]
+    fn invalid_bindings() {
+        match Bindings::from_toml("toggle_wirefrme = [\"F\"]") {
+            Err(BindingsError::UnknownAction(ref name)) if name == "toggle_wirefrme" => (),
+            result => panic!("{:?}", result.map(|_| ())),
+        }
+        match Bindings::from_toml("pause = [\"Pause\"]") {
+            Err(BindingsError::UnknownKey(ref name)) if name == "Pause" => (),
+            result => panic!("{:?}", result.map(|_| ())),
+        }
+        match Bindings::from_toml("pause = \"P\"") {
+            Err(BindingsError::Toml(_)) => (),
+            result => panic!("{:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn missing_bindings_file() {
+        assert!(Bindings::load_or_default(None).is_ok());
+        assert!(Bindings::load_or_default(Some("does/not/exist.toml")).is_ok());
+    }
+}
@@ -0,0 +1,59 @@
+//! Log targets per subsystem and timing spans, on top of the `log` crate.
+//!
+//! `RUST_LOG` selects what's printed, e.g. `RUST_LOG=debug` for all of initialization or
+//! `RUST_LOG=swapchain=debug,frame=trace` for swap chain changes and the phases of each frame.
+//! Without it only warnings, errors and the `SUMMARY` are printed.
+use std::time::Instant;
+
+use env_logger;
+use log::Level;
+
+pub const INSTANCE: &str = "instance";
+/// also the validation layer messages
+pub const VALIDATION: &str = "validation";
+/// device selection and everything allocated from the device
+pub const DEVICE: &str = "device";
+pub const SWAPCHAIN: &str = "swapchain";
+pub const PIPELINE: &str = "pipeline";
+pub const FRAME: &str = "frame";
+/// key bindings
+pub const INPUT: &str = "input";
+/// results the user asked for (frame stats, written files), printed by default
+pub const SUMMARY: &str = "summary";
+
+pub fn init() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(format!("warn,{}=info", SUMMARY))).init();
+}
+
+/// Logs when it starts and (when dropped) how long it took.
+pub struct Span {
+    target: &'static str,
+    level: Level,
+    name: &'static str,
+    start: Instant,
+}
+
+impl Span {
+    pub fn new(target: &'static str, level: Level, name: &'static str) -> Self {
+        log!(target: target, level, "{}...", name);
+        Self { target, level, name, start: Instant::now() }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        log!(target: self.target, self.level, "{} took {:.3}ms", self.name,
+            elapsed.as_secs() as f32 * 1000.0 + elapsed.subsec_nanos() as f32 / 1_000_000.0);
+    }
+}
+
+/// A step of `initialize` (or of rebuilding something later), at debug level.
+pub fn init_span(target: &'static str, name: &'static str) -> Span {
+    Span::new(target, Level::Debug, name)
+}
+
+/// A phase of `draw_frame`, at trace level.
+pub fn frame_span(name: &'static str) -> Span {
+    Span::new(FRAME, Level::Trace, name)
+}
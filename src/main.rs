@@ -2,17 +2,62 @@
 extern crate vulkano;
 extern crate vulkano_win;
 extern crate winit;
+extern crate cgmath;
+extern crate shaderc;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+extern crate toml;
+extern crate serde_json;
+extern crate vk_sys as vk;
+
+#[macro_use]
+mod reflection;
+mod error;
+mod logging;
+mod pipeline_description;
+mod pipeline_cache;
+mod scene;
+mod object_uniforms;
+mod mesh_upload;
+mod upload_ring;
+mod frame_stats;
+mod gpu_timer;
+mod benchmark;
+mod clock;
+mod projection;
+mod camera;
+mod input;
+mod redraw;
+mod resource_tracker;
+mod recovery;
+mod config;
+mod api_version;
 
 use std::sync::Arc;
 use std::collections::HashSet;
-
-use winit::{EventsLoop, WindowBuilder, Window, dpi::LogicalSize, Event, WindowEvent};
+use std::time::{Duration, Instant};
+use std::thread;
+use std::process;
+
+use log::Level;
+use winit::{
+    EventsLoop,
+    WindowBuilder,
+    Window,
+    dpi::LogicalSize,
+    Event,
+    WindowEvent,
+    ControlFlow,
+};
 use vulkano_win::VkSurfaceBuild;
 
 use vulkano::instance::{
     Instance,
     InstanceExtensions,
-    ApplicationInfo,
     Version,
     layers_list,
     PhysicalDevice,
@@ -29,14 +74,16 @@ use vulkano::swapchain::{
     CompositeAlpha,
     acquire_next_image,
     AcquireError,
+    SwapchainCreationError,
 };
-use vulkano::format::Format;
-use vulkano::image::{ImageUsage, swapchain::SwapchainImage};
+use vulkano::format::{Format, ClearValue};
+use vulkano::image::{AttachmentImage, ImageUsage, swapchain::SwapchainImage};
 use vulkano::sync::{self, SharingMode, GpuFuture};
 use vulkano::pipeline::{
     GraphicsPipeline,
     GraphicsPipelineAbstract,
     viewport::Viewport,
+    vertex::OneVertexOneInstanceDefinition,
 };
 use vulkano::framebuffer::{
     RenderPassAbstract,
@@ -50,14 +97,38 @@ use vulkano::command_buffer::{
     DynamicState,
 };
 use vulkano::buffer::{
-    immutable::ImmutableBuffer,
     BufferUsage,
     BufferAccess,
     TypedBufferAccess,
+    DeviceLocalBuffer,
+};
+use vulkano::descriptor::descriptor_set::{
+    DescriptorSet,
+    PersistentDescriptorSet,
+};
+use cgmath::{
+    Rad,
+    Deg,
+    Matrix4,
 };
 
-const WIDTH: u32 = 800;
-const HEIGHT: u32 = 600;
+use error::Error;
+use pipeline_description::{PipelineDescription, PolygonMode};
+use pipeline_cache::PipelineCache;
+use scene::{Scene, SceneObject, DrawMode, animate_object, animate_instances};
+use object_uniforms::ObjectUniformBuffer;
+use mesh_upload::{Mesh, UploadedMesh};
+use upload_ring::{UploadRing, UploadStats};
+use frame_stats::{FrameStats, FrameTimes};
+use gpu_timer::GpuTimer;
+use benchmark::{Benchmark, DeviceInfo, FrameRecord};
+use clock::{AnimationTime, FixedStepClock};
+use camera::Camera;
+use input::{Action, Bindings, InputState};
+use redraw::{RedrawMode, FrameLimiter};
+use resource_tracker::{ResourceKind, ResourceTracker};
+use recovery::{Lost, Recovery};
+use config::{Config, FrameConfig, PresentModeSetting};
 
 const VALIDATION_LAYERS: &[&str] =  &[
     "VK_LAYER_LUNARG_standard_validation"
@@ -71,11 +142,6 @@ fn device_extensions() -> DeviceExtensions {
     }
 }
 
-#[cfg(all(debug_assertions))]
-const ENABLE_VALIDATION_LAYERS: bool = true;
-#[cfg(not(debug_assertions))]
-const ENABLE_VALIDATION_LAYERS: bool = false;
-
 struct QueueFamilyIndices {
     graphics_family: i32,
     present_family: i32,
@@ -102,6 +168,187 @@ impl Vertex {
 }
 impl_vertex!(Vertex, pos, color);
 
+/// Field names passed to `impl_vertex!`, in shader location order.
+const VERTEX_ATTRIBUTES: &[&str] = &["pos", "color"];
+
+/// Per-object uniforms of `object_uniforms.vert`; an array of them is in one `ObjectUniformBuffer`.
+/// NOTE: the size needs to match the array stride in the shader (std430, a multiple of 16 here)
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ObjectUniforms {
+    model: Matrix4<f32>,
+    tint: [f32; 4],
+}
+
+/// Per-instance vertex input of `instanced.vert`; the second vertex buffer of the instanced pipeline.
+#[derive(Copy, Clone)]
+struct InstanceData {
+    instance_offset: [f32; 2],
+    instance_scale: f32,
+    instance_color: [f32; 3],
+}
+impl_vertex!(InstanceData, instance_offset, instance_scale, instance_color);
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct UniformBufferObject {
+    model: Matrix4<f32>,
+    view: Matrix4<f32>,
+    proj: Matrix4<f32>,
+}
+
+// NOTE: outside of `create_graphics_pipeline` (unlike the tutorial), because we need
+// the generated `vertex_shader::ty::PushConstants` when recording the command buffers
+mod vertex_shader {
+    vulkano_shaders::shader! {
+       ty: "vertex",
+       path: "src/shaders/shader.vert"
+    }
+}
+
+mod instanced_vertex_shader {
+    vulkano_shaders::shader! {
+       ty: "vertex",
+       path: "src/shaders/instanced.vert"
+    }
+}
+
+mod object_uniforms_vertex_shader {
+    vulkano_shaders::shader! {
+       ty: "vertex",
+       path: "src/shaders/object_uniforms.vert"
+    }
+}
+
+mod fragment_shader {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/shaders/shader.frag"
+    }
+}
+
+/// The entries of the `ObjectUniformBuffer` at the given time.
+fn object_uniforms(scene: &Scene, seconds: f32) -> impl ExactSizeIterator<Item=ObjectUniforms> + '_ {
+    scene.objects.iter().enumerate()
+        .map(move |(i, object)| ObjectUniforms { model: animate_object(i, object, seconds), tint: object.tint })
+}
+
+/// Per-draw data for the push constant block of the vertex shader.
+/// The generated struct always matches the GLSL declaration.
+fn push_constants(object: &SceneObject) -> vertex_shader::ty::PushConstants {
+    vertex_shader::ty::PushConstants {
+        model: object.model.into(),
+        tint: object.tint,
+    }
+}
+
+/// The push constants of a draw call, for the vertex shader of the draw mode.
+#[derive(Copy, Clone)]
+enum DrawPushConstants {
+    /// `DrawMode::Instanced` doesn't use any
+    None,
+    /// `DrawMode::PushConstants`: all per-object data
+    Object(vertex_shader::ty::PushConstants),
+    /// `DrawMode::StorageBuffer`: the index of the object's entry in the `ObjectUniformBuffer`
+    ObjectIndex(object_uniforms_vertex_shader::ty::PushConstants),
+}
+
+/// The arguments of one `draw_indexed` call. Owns its buffers and descriptor sets,
+/// so draw calls can be recorded on other threads.
+#[derive(Clone)]
+struct DrawCall {
+    vertex_buffers: Vec<Arc<BufferAccess + Send + Sync>>,
+    index_buffer: Arc<TypedBufferAccess<Content=[u16]> + Send + Sync>,
+    descriptor_sets: Vec<Arc<DescriptorSet + Send + Sync>>,
+    push_constants: DrawPushConstants,
+}
+
+/// Returns vulkano's error message on failure (see `Error::CommandBuffer`), which can also be
+/// passed back from the recording threads.
+fn record_draw_calls(
+    mut builder: AutoCommandBufferBuilder,
+    graphics_pipeline: &Arc<GraphicsPipelineAbstract + Send + Sync>,
+    draw_calls: &[DrawCall],
+) -> Result<AutoCommandBufferBuilder, String> {
+    for draw_call in draw_calls {
+        let DrawCall { vertex_buffers, index_buffer, descriptor_sets, push_constants } = draw_call.clone();
+        builder = match push_constants {
+            DrawPushConstants::None => builder.draw_indexed(graphics_pipeline.clone(), &DynamicState::none(),
+                vertex_buffers, index_buffer, descriptor_sets, ()),
+            DrawPushConstants::Object(push_constants) => builder.draw_indexed(graphics_pipeline.clone(),
+                &DynamicState::none(), vertex_buffers, index_buffer, descriptor_sets, push_constants),
+            DrawPushConstants::ObjectIndex(push_constants) => builder.draw_indexed(graphics_pipeline.clone(),
+                &DynamicState::none(), vertex_buffers, index_buffer, descriptor_sets, push_constants),
+        }.map_err(|err| err.to_string())?;
+    }
+    Ok(builder)
+}
+
+/// Values for the specialization constants of both shaders. Each combination is a separate
+/// pipeline, so this is part of the pipeline cache key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct ShaderSpecialization {
+    use_vertex_color: bool,
+    /// 0 = no posterization
+    color_levels: u32,
+}
+
+impl Default for ShaderSpecialization {
+    fn default() -> Self {
+        Self { use_vertex_color: true, color_levels: 0 }
+    }
+}
+
+impl ShaderSpecialization {
+    fn vertex(&self) -> vertex_shader::SpecializationConstants {
+        vertex_shader::SpecializationConstants { use_vertex_color: self.use_vertex_color as u32 }
+    }
+
+    fn instanced_vertex(&self) -> instanced_vertex_shader::SpecializationConstants {
+        instanced_vertex_shader::SpecializationConstants { use_vertex_color: self.use_vertex_color as u32 }
+    }
+
+    fn object_uniforms_vertex(&self) -> object_uniforms_vertex_shader::SpecializationConstants {
+        object_uniforms_vertex_shader::SpecializationConstants { use_vertex_color: self.use_vertex_color as u32 }
+    }
+
+    fn fragment(&self) -> fragment_shader::SpecializationConstants {
+        fragment_shader::SpecializationConstants { color_levels: self.color_levels as i32 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    /// selects the vertex shader and vertex input (`shader.vert`, `object_uniforms.vert` or `instanced.vert`)
+    draw_mode: DrawMode,
+    description: PipelineDescription,
+    specialization: ShaderSpecialization,
+}
+
+/// How the command buffer for a frame is obtained.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum RecordingMode {
+    /// One `primary_simultaneous_use` buffer per swap chain image, rebuilt on every change (like the tutorial)
+    Prebuilt,
+    /// A fresh `primary_one_time_submit` buffer for the acquired image in every frame
+    PerFrame,
+}
+
+impl RecordingMode {
+    /// `frame.record_per_frame` in the config (`--record-per-frame`) selects `PerFrame`
+    fn from_config(config: &FrameConfig) -> Self {
+        if config.record_per_frame {
+            RecordingMode::PerFrame
+        } else {
+            RecordingMode::Prebuilt
+        }
+    }
+}
+
+fn milliseconds(duration: Duration) -> f32 {
+    duration.as_secs() as f32 * 1000.0 + duration.subsec_nanos() as f32 / 1_000_000.0
+}
+
 fn vertices() -> [Vertex; 4] {
     [
         Vertex::new([-0.5, -0.5], [1.0, 0.0, 0.0]),
@@ -115,6 +362,29 @@ fn indices() -> [u16; 6] {
     [0, 1, 2, 2, 3, 0]
 }
 
+/// A triangle fan with the same winding and size as the quad.
+fn regular_polygon(sides: usize) -> Mesh<Vertex> {
+    let mut vertices = vec![Vertex::new([0.0, 0.0], [1.0, 1.0, 1.0])];
+    let mut indices = Vec::new();
+    for i in 0..sides {
+        let angle = i as f32 / sides as f32 * 2.0 * std::f32::consts::PI;
+        let color = [angle.cos() * 0.5 + 0.5, angle.sin() * 0.5 + 0.5, 0.5];
+        vertices.push(Vertex::new([angle.cos() * 0.5, angle.sin() * 0.5], color));
+        indices.extend_from_slice(&[0, i as u16 + 1, ((i + 1) % sides) as u16 + 1]);
+    }
+    Mesh { vertices, indices }
+}
+
+/// The quad of the tutorial first, then a few other shapes for scenes with multiple objects.
+fn meshes() -> Vec<Mesh<Vertex>> {
+    vec![
+        Mesh { vertices: vertices().to_vec(), indices: indices().to_vec() },
+        regular_polygon(3),
+        regular_polygon(6),
+        regular_polygon(8),
+    ]
+}
+
 struct HelloTriangleApplication {
     instance: Arc<Instance>,
     #[allow(unused)]
@@ -124,6 +394,8 @@ struct HelloTriangleApplication {
     surface: Arc<Surface<Window>>,
 
     physical_device_index: usize, // can't store PhysicalDevice directly (lifetime issues)
+    /// what the app can rely on, see `api_version::negotiate`
+    api_version: Version,
     device: Arc<Device>,
 
     graphics_queue: Arc<Queue>,
@@ -132,42 +404,146 @@ struct HelloTriangleApplication {
     swap_chain: Arc<Swapchain<Window>>,
     swap_chain_images: Vec<Arc<SwapchainImage<Window>>>,
 
+    /// `config.msaa_samples`, lowered to what the device supports
+    msaa_samples: u32,
     render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    /// from `config.pipeline`; what the wireframe toggle switches back to
+    configured_pipeline_description: PipelineDescription,
+    pipeline_description: PipelineDescription,
+    shader_specialization: ShaderSpecialization,
+    pipeline_cache: PipelineCache<PipelineKey>,
     graphics_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
 
     swap_chain_framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
 
-    vertex_buffer: Arc<BufferAccess + Send + Sync>,
-    index_buffer: Arc<TypedBufferAccess<Content=[u16]> + Send + Sync>,
+    meshes: Vec<UploadedMesh>,
+
+    uniform_buffers: Vec<Arc<DeviceLocalBuffer<UniformBufferObject>>>,
+    descriptor_sets: Vec<Arc<DescriptorSet + Send + Sync>>,
+
+    scene: Scene,
+    instance_buffers: Vec<Arc<DeviceLocalBuffer<[InstanceData]>>>,
+    object_uniform_buffers: Vec<ObjectUniformBuffer<ObjectUniforms>>,
+    /// set 1 of `DrawMode::StorageBuffer`, one per swap chain image
+    object_descriptor_sets: Vec<Arc<DescriptorSet + Send + Sync>>,
+
+    uniform_ring: UploadRing<UniformBufferObject>,
+    instance_ring: UploadRing<InstanceData>,
+    object_ring: UploadRing<ObjectUniforms>,
+    upload_stats: UploadStats,
+
+    recording_mode: RecordingMode,
+    recording_threads: usize,
+    /// the `--threads` value, `recording_threads` toggles between it and 1
+    configured_recording_threads: usize,
     command_buffers: Vec<Arc<AutoCommandBuffer>>,
+    frame_stats: FrameStats,
+    frame_times: FrameTimes,
+    csv_path: Option<String>,
+    /// `None` if the device doesn't support timestamp queries
+    gpu_timer: Option<GpuTimer>,
+    last_stats_report: Instant,
 
     previous_frame_end: Option<Box<GpuFuture>>,
     recreate_swap_chain: bool,
+    recovery: Recovery,
+
+    time: AnimationTime,
+    camera: Camera,
+    input: InputState,
+    bindings: Bindings,
+    redraw_mode: RedrawMode,
+    /// only in continuous mode
+    frame_limiter: Option<FrameLimiter>,
+    benchmark: Option<Benchmark>,
+    config: Config,
 }
 
 impl HelloTriangleApplication {
-    pub fn initialize() -> Self {
-        let instance = Self::create_instance();
-        let debug_callback = Self::setup_debug_callback(&instance);
-        let (events_loop, surface) = Self::create_surface(&instance);
+    pub fn initialize(config: Config) -> Result<Self, Error> {
+        // NOTE: not part of the tutorial - catches mismatches between the shaders and
+        // `Vertex`/`UniformBufferObject` that validation layers don't report (or only as garbage on screen)
+        // The shader sources might not be there (e.g. when run outside of the source tree), so that's
+        // only a warning: the app uses the SPIR-V compiled into it.
+        if config.validation {
+            match check_shader_interface(&config.assets.shader_dir) {
+                Ok(mismatches) => for mismatch in mismatches {
+                    warn!(target: logging::PIPELINE, "shader interface mismatch: {}", mismatch);
+                },
+                Err(err) => warn!(target: logging::PIPELINE, "skipped the shader interface check: {}", err),
+            }
+        }
 
-        let physical_device_index = Self::pick_physical_device(&instance, &surface);
+        let instance = Self::create_instance(&config)?;
+        let debug_callback = Self::setup_debug_callback(&instance, config.validation);
+        let (events_loop, surface) = Self::create_surface(&instance, &config)?;
+
+        let physical_device_index = Self::pick_physical_device(&instance, &surface, &config)?;
+        let api_version = Self::negotiate_api_version(&instance, physical_device_index, &config);
+        info!(target: logging::DEVICE, "using Vulkan {}", api_version::to_string(api_version));
         let (device, graphics_queue, present_queue) = Self::create_logical_device(
-            &instance, &surface, physical_device_index);
+            &instance, &surface, physical_device_index)?;
 
         let (swap_chain, swap_chain_images) = Self::create_swap_chain(&instance, &surface, physical_device_index,
-            &device, &graphics_queue, &present_queue, None);
+            &device, &graphics_queue, &present_queue, &config, None)?;
+
+        let msaa_samples = Self::choose_sample_count(&instance, physical_device_index, config.msaa_samples);
+        let render_pass = Self::create_render_pass(&device, swap_chain.format(), msaa_samples)?;
+
+        let scene = Scene::from_config(&config.scene);
+
+        let configured_pipeline_description = PipelineDescription::load(&config.pipeline)
+            .map_err(Error::PipelineDescription)?;
+        let pipeline_description = configured_pipeline_description.clone();
+        let shader_specialization = ShaderSpecialization::default();
+        let mut pipeline_cache = PipelineCache::new();
+        let pipeline_key = PipelineKey {
+            draw_mode: scene.draw_mode,
+            description: pipeline_description.clone(),
+            specialization: shader_specialization,
+        };
+        let graphics_pipeline = pipeline_cache.get_or_create(&pipeline_key,
+            |key| Self::create_graphics_pipeline(&device, swap_chain.dimensions(), &render_pass, key))?;
+
+        let swap_chain_framebuffers = Self::create_framebuffers(&device, &swap_chain_images, swap_chain.format(),
+            &render_pass, msaa_samples)?;
 
-        let render_pass = Self::create_render_pass(&device, swap_chain.format());
-        let graphics_pipeline = Self::create_graphics_pipeline(&device, swap_chain.dimensions(), &render_pass);
+        let meshes = Self::create_mesh_buffers(&graphics_queue)?;
+        let uniform_buffers = Self::create_uniform_buffers(&device, &graphics_queue, swap_chain_images.len())?;
+        let descriptor_sets = Self::create_descriptor_sets(&graphics_pipeline, &uniform_buffers)?;
+        let instance_buffers = Self::create_instance_buffers(&device, &graphics_queue, swap_chain_images.len(),
+            &scene)?;
+        let object_uniform_buffers = Self::create_object_uniform_buffers(&device, &graphics_queue,
+            swap_chain_images.len(), &scene)?;
+        let object_descriptor_sets = Self::create_object_descriptor_sets(&graphics_pipeline,
+            &object_uniform_buffers)?;
 
-        let swap_chain_framebuffers = Self::create_framebuffers(&swap_chain_images, &render_pass);
+        let uniform_ring = UploadRing::new(&device);
+        let instance_ring = UploadRing::new(&device);
+        let object_ring = UploadRing::new(&device);
 
-        let vertex_buffer = Self::create_vertex_buffer(&graphics_queue);
-        let index_buffer = Self::create_index_buffer(&graphics_queue);
+        let gpu_timer = Self::create_gpu_timer(&instance, physical_device_index, &device, &graphics_queue,
+            swap_chain_images.len());
 
         let previous_frame_end = Some(Self::create_sync_objects(&device));
 
+        let camera = Camera::new(swap_chain.dimensions());
+        let benchmark = config.benchmark.clone().map(Benchmark::new);
+        // NOTE: benchmarks always render the same frames
+        let time = if benchmark.is_some() {
+            AnimationTime::new(Box::new(FixedStepClock::default()))
+        } else {
+            AnimationTime::from_config(config.frame.clock)
+        };
+        let redraw_mode = if benchmark.is_some() { RedrawMode::Continuous } else { RedrawMode::from_config(&config.frame) };
+        let frame_limiter = match redraw_mode {
+            RedrawMode::Continuous if benchmark.is_none() => FrameLimiter::from_config(&config.frame),
+            _ => None,
+        };
+        let bindings = Bindings::load_or_default(config.assets.bindings.as_ref().map(String::as_str))?;
+        let recording_threads = config.frame.threads;
+        let csv_path = config.frame.csv.clone();
+
         let mut app = Self {
             instance,
             debug_callback,
@@ -176,6 +552,7 @@ impl HelloTriangleApplication {
             surface,
 
             physical_device_index,
+            api_version,
             device,
 
             graphics_queue,
@@ -184,59 +561,95 @@ impl HelloTriangleApplication {
             swap_chain,
             swap_chain_images,
 
+            msaa_samples,
             render_pass,
+            configured_pipeline_description,
+            pipeline_description,
+            shader_specialization,
+            pipeline_cache,
             graphics_pipeline,
 
             swap_chain_framebuffers,
 
-            vertex_buffer,
-            index_buffer,
+            meshes,
+            uniform_buffers,
+            descriptor_sets,
+
+            scene,
+            instance_buffers,
+            object_uniform_buffers,
+            object_descriptor_sets,
+
+            uniform_ring,
+            instance_ring,
+            object_ring,
+            upload_stats: UploadStats::default(),
+
+            recording_mode: RecordingMode::from_config(&config.frame),
+            recording_threads,
+            configured_recording_threads: recording_threads,
             command_buffers: vec![],
+            frame_stats: FrameStats::default(),
+            frame_times: FrameTimes::new(csv_path.is_some()),
+            csv_path,
+            gpu_timer,
+            last_stats_report: Instant::now(),
 
             previous_frame_end,
             recreate_swap_chain: false,
+            recovery: Recovery::default(),
+
+            time,
+            camera,
+            input: InputState::default(),
+            bindings,
+            redraw_mode,
+            frame_limiter,
+            benchmark,
+            config,
         };
 
-        app.create_command_buffers();
-        app
+        app.create_command_buffers()?;
+        Ok(app)
     }
 
-    fn create_instance() -> Arc<Instance> {
-        if ENABLE_VALIDATION_LAYERS && !Self::check_validation_layer_support() {
-            println!("Validation layers requested, but not available!")
+    fn create_instance(config: &Config) -> Result<Arc<Instance>, Error> {
+        let _span = logging::init_span(logging::INSTANCE, "create_instance");
+        let validation = config.validation;
+        if validation && !Self::check_validation_layer_support() {
+            warn!(target: logging::INSTANCE, "Validation layers requested, but not available!")
         }
 
-        let supported_extensions = InstanceExtensions::supported_by_core()
-            .expect("failed to retrieve supported extensions");
-        println!("Supported extensions: {:?}", supported_extensions);
+        match InstanceExtensions::supported_by_core() {
+            Ok(supported_extensions) =>
+                debug!(target: logging::INSTANCE, "Supported extensions: {:?}", supported_extensions),
+            Err(err) => warn!(target: logging::INSTANCE, "failed to retrieve supported extensions: {}", err),
+        }
 
-        let app_info = ApplicationInfo {
-            application_name: Some("Hello Triangle".into()),
-            application_version: Some(Version { major: 1, minor: 0, patch: 0 }),
-            engine_name: Some("No Engine".into()),
-            engine_version: Some(Version { major: 1, minor: 0, patch: 0 }),
-        };
+        let app_info = config.application_info();
 
-        let required_extensions = Self::get_required_extensions();
+        let required_extensions = Self::get_required_extensions(validation);
 
-        if ENABLE_VALIDATION_LAYERS && Self::check_validation_layer_support() {
+        let instance = if validation && Self::check_validation_layer_support() {
             Instance::new(Some(&app_info), &required_extensions, VALIDATION_LAYERS.iter().cloned())
-                .expect("failed to create Vulkan instance")
         } else {
             Instance::new(Some(&app_info), &required_extensions, None)
-                .expect("failed to create Vulkan instance")
-        }
+        };
+        instance.map_err(Error::Instance)
     }
 
     fn check_validation_layer_support() -> bool {
-        let layers: Vec<_> = layers_list().unwrap().map(|l| l.name().to_owned()).collect();
+        let layers: Vec<_> = match layers_list() {
+            Ok(layers) => layers.map(|l| l.name().to_owned()).collect(),
+            Err(_) => return false,
+        };
         VALIDATION_LAYERS.iter()
             .all(|layer_name| layers.contains(&layer_name.to_string()))
     }
 
-    fn get_required_extensions() -> InstanceExtensions {
+    fn get_required_extensions(validation: bool) -> InstanceExtensions {
         let mut extensions = vulkano_win::required_extensions();
-        if ENABLE_VALIDATION_LAYERS {
+        if validation {
             // TODO!: this should be ext_debug_utils (_report is deprecated), but that doesn't exist yet in vulkano
             extensions.ext_debug_report = true;
         }
@@ -244,8 +657,9 @@ impl HelloTriangleApplication {
         extensions
     }
 
-    fn setup_debug_callback(instance: &Arc<Instance>) -> Option<DebugCallback> {
-        if !ENABLE_VALIDATION_LAYERS  {
+    fn setup_debug_callback(instance: &Arc<Instance>, validation: bool) -> Option<DebugCallback> {
+        let _span = logging::init_span(logging::INSTANCE, "setup_debug_callback");
+        if !validation {
             return None;
         }
 
@@ -253,29 +667,66 @@ impl HelloTriangleApplication {
             error: true,
             warning: true,
             performance_warning: true,
-            information: false,
+            // NOTE: quiet unless the `validation` target is logged at debug level
+            information: true,
             debug: true,
         };
         DebugCallback::new(&instance, msg_types, |msg| {
-            println!("validation layer: {:?}", msg.description);
+            let level = if msg.ty.error {
+                Level::Error
+            } else if msg.ty.warning || msg.ty.performance_warning {
+                Level::Warn
+            } else {
+                Level::Debug
+            };
+            log!(target: logging::VALIDATION, level, "{}: {}", msg.layer_prefix, msg.description);
         }).ok()
     }
 
-    fn pick_physical_device(instance: &Arc<Instance>, surface: &Arc<Surface<Window>>) -> usize {
+    fn pick_physical_device(instance: &Arc<Instance>, surface: &Arc<Surface<Window>>, config: &Config)
+        -> Result<usize, Error>
+    {
+        let _span = logging::init_span(logging::DEVICE, "pick_physical_device");
+        let selection = config.device.as_ref().map(String::as_str);
         PhysicalDevice::enumerate(&instance)
-            .position(|device| Self::is_device_suitable(surface, &device))
-            .expect("failed to find a suitable GPU!")
+            .position(|device|
+                Self::is_device_selected(&device, selection) && Self::is_device_suitable(surface, &device))
+            .ok_or(Error::NoSuitableGpu)
+    }
+
+    /// NOTE: always 1.0 with vulkano 0.11 (see `api_version`)
+    fn negotiate_api_version(instance: &Arc<Instance>, physical_device_index: usize, config: &Config) -> Version {
+        let physical_device = PhysicalDevice::from_index(&instance, physical_device_index).unwrap();
+        let requested = config.requested_api_version();
+        let (api_version, limited_by) = api_version::negotiate(requested, physical_device.api_version());
+        if let Some(limited_by) = limited_by {
+            info!(target: logging::INSTANCE, "Vulkan {}.{} requested, but {} only supports {}.{}",
+                requested.major, requested.minor, limited_by, api_version.major, api_version.minor);
+        }
+        api_version
+    }
+
+    /// `selection` is the index of the device or part of its name (ignoring case); any device if not set.
+    fn is_device_selected(device: &PhysicalDevice, selection: Option<&str>) -> bool {
+        match selection {
+            None => true,
+            Some(selection) => match selection.parse::<usize>() {
+                Ok(index) => device.index() == index,
+                Err(_) => device.name().to_lowercase().contains(&selection.to_lowercase()),
+            },
+        }
     }
 
     fn is_device_suitable(surface: &Arc<Surface<Window>>, device: &PhysicalDevice) -> bool {
         let indices = Self::find_queue_families(surface, device);
         let extensions_supported = Self::check_device_extension_support(device);
 
+        // NOTE: a device whose capabilities can't be queried isn't suitable either
         let swap_chain_adequate = if extensions_supported {
-                let capabilities = surface.capabilities(*device)
-                    .expect("failed to get surface capabilities");
-                !capabilities.supported_formats.is_empty() &&
-                    capabilities.present_modes.iter().next().is_some()
+                surface.capabilities(*device)
+                    .map(|capabilities| !capabilities.supported_formats.is_empty() &&
+                        capabilities.present_modes.iter().next().is_some())
+                    .unwrap_or(false)
             } else {
                 false
             };
@@ -299,7 +750,16 @@ impl HelloTriangleApplication {
             .unwrap_or_else(|| &available_formats[0])
     }
 
-    fn choose_swap_present_mode(available_present_modes: SupportedPresentModes) -> PresentMode {
+    fn choose_swap_present_mode(available_present_modes: SupportedPresentModes, preferred: PresentModeSetting)
+        -> PresentMode
+    {
+        if let Some(present_mode) = preferred.present_mode() {
+            if available_present_modes.iter().any(|available| available == present_mode) {
+                return present_mode;
+            }
+            warn!(target: logging::SWAPCHAIN, "present mode {:?} not supported, choosing another one", present_mode);
+        }
+
         if available_present_modes.mailbox {
             PresentMode::Mailbox
         } else if available_present_modes.immediate {
@@ -309,11 +769,11 @@ impl HelloTriangleApplication {
         }
     }
 
-    fn choose_swap_extent(capabilities: &Capabilities) -> [u32; 2] {
+    fn choose_swap_extent(capabilities: &Capabilities, window_size: [u32; 2]) -> [u32; 2] {
         if let Some(current_extent) = capabilities.current_extent {
             return current_extent
         } else {
-            let mut actual_extent = [WIDTH, HEIGHT];
+            let mut actual_extent = window_size;
             actual_extent[0] = capabilities.min_image_extent[0]
                 .max(capabilities.max_image_extent[0].min(actual_extent[0]));
             actual_extent[1] = capabilities.min_image_extent[1]
@@ -329,15 +789,17 @@ impl HelloTriangleApplication {
         device: &Arc<Device>,
         graphics_queue: &Arc<Queue>,
         present_queue: &Arc<Queue>,
+        config: &Config,
         old_swapchain: Option<Arc<Swapchain<Window>>>,
-    ) -> (Arc<Swapchain<Window>>, Vec<Arc<SwapchainImage<Window>>>) {
+    ) -> Result<(Arc<Swapchain<Window>>, Vec<Arc<SwapchainImage<Window>>>), Error> {
+        let _span = logging::init_span(logging::SWAPCHAIN, "create_swap_chain");
         let physical_device = PhysicalDevice::from_index(&instance, physical_device_index).unwrap();
         let capabilities = surface.capabilities(physical_device)
-            .expect("failed to get surface capabilities");
+            .map_err(Error::SurfaceCapabilities)?;
 
         let surface_format = Self::choose_swap_surface_format(&capabilities.supported_formats);
-        let present_mode = Self::choose_swap_present_mode(capabilities.present_modes);
-        let extent = Self::choose_swap_extent(&capabilities);
+        let present_mode = Self::choose_swap_present_mode(capabilities.present_modes, config.present_mode);
+        let extent = Self::choose_swap_extent(&capabilities, [config.window.width, config.window.height]);
 
         let mut image_count = capabilities.min_image_count + 1;
         if capabilities.max_image_count.is_some() && image_count > capabilities.max_image_count.unwrap() {
@@ -371,13 +833,56 @@ impl HelloTriangleApplication {
             present_mode,
             true, // clipped
             old_swapchain.as_ref()
-        ).expect("failed to create swap chain!");
+        ).map_err(Error::Swapchain)?;
+
+        Ok((swap_chain, images))
+    }
 
-        (swap_chain, images)
+    /// The highest sample count the device supports for color attachments, up to `requested`.
+    fn choose_sample_count(instance: &Arc<Instance>, physical_device_index: usize, requested: u32) -> u32 {
+        let physical_device = PhysicalDevice::from_index(&instance, physical_device_index).unwrap();
+        let supported = physical_device.limits().framebuffer_color_sample_counts();
+        let samples = [64, 32, 16, 8, 4, 2, 1].iter().cloned()
+            .find(|&samples| samples <= requested && supported & samples != 0)
+            .unwrap_or(1);
+        if samples != requested {
+            warn!(target: logging::DEVICE, "{} MSAA samples not supported, using {}", requested, samples);
+        }
+        samples
     }
 
-    fn create_render_pass(device: &Arc<Device>, color_format: Format) -> Arc<RenderPassAbstract + Send + Sync> {
-        Arc::new(single_pass_renderpass!(device.clone(),
+    fn create_render_pass(device: &Arc<Device>, color_format: Format, samples: u32)
+        -> Result<Arc<RenderPassAbstract + Send + Sync>, Error>
+    {
+        let _span = logging::init_span(logging::PIPELINE, "create_render_pass");
+        if samples > 1 {
+            // NOTE: not part of the tutorial - renders into a multisampled image, which is resolved
+            // into the swap chain image at the end of the subpass
+            let render_pass = single_pass_renderpass!(device.clone(),
+                attachments: {
+                    multisampled: {
+                        load: Clear,
+                        store: DontCare,
+                        format: color_format,
+                        samples: samples,
+                    },
+                    color: {
+                        load: DontCare,
+                        store: Store,
+                        format: color_format,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [multisampled],
+                    depth_stencil: {},
+                    resolve: [color],
+                }
+            ).map_err(Error::RenderPass)?;
+            return Ok(Arc::new(render_pass));
+        }
+
+        let render_pass = single_pass_renderpass!(device.clone(),
             attachments: {
                 color: {
                     load: Clear,
@@ -390,32 +895,31 @@ impl HelloTriangleApplication {
                 color: [color],
                 depth_stencil: {}
             }
-        ).unwrap())
+        ).map_err(Error::RenderPass)?;
+        Ok(Arc::new(render_pass))
     }
 
     fn create_graphics_pipeline(
         device: &Arc<Device>,
         swap_chain_extent: [u32; 2],
         render_pass: &Arc<RenderPassAbstract + Send + Sync>,
-    ) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
-        mod vertex_shader {
-            vulkano_shaders::shader! {
-               ty: "vertex",
-               path: "src/bin/17_shader_vertexbuffer.vert"
-            }
+        key: &PipelineKey,
+    ) -> Result<Arc<GraphicsPipelineAbstract + Send + Sync>, Error> {
+        let _span = logging::init_span(logging::PIPELINE, "create_graphics_pipeline");
+        match key.draw_mode {
+            DrawMode::PushConstants => (),
+            DrawMode::StorageBuffer =>
+                return Self::create_object_uniforms_graphics_pipeline(device, swap_chain_extent, render_pass, key),
+            DrawMode::Instanced =>
+                return Self::create_instanced_graphics_pipeline(device, swap_chain_extent, render_pass, key),
         }
 
-        mod fragment_shader {
-            vulkano_shaders::shader! {
-                ty: "fragment",
-                path: "src/bin/17_shader_vertexbuffer.frag"
-            }
-        }
+        let PipelineKey { description, specialization, .. } = key;
 
         let vert_shader_module = vertex_shader::Shader::load(device.clone())
-            .expect("failed to create vertex shader module!");
+            .map_err(Error::Shader)?;
         let frag_shader_module = fragment_shader::Shader::load(device.clone())
-            .expect("failed to create fragment shader module!");
+            .map_err(Error::Shader)?;
 
         let dimensions = [swap_chain_extent[0] as f32, swap_chain_extent[1] as f32];
         let viewport = Viewport {
@@ -424,77 +928,409 @@ impl HelloTriangleApplication {
             depth_range: 0.0 .. 1.0,
         };
 
-        Arc::new(GraphicsPipeline::start()
+        let builder = GraphicsPipeline::start()
             .vertex_input_single_buffer::<Vertex>()
-            .vertex_shader(vert_shader_module.main_entry_point(), ())
-            .triangle_list()
+            .vertex_shader(vert_shader_module.main_entry_point(), specialization.vertex())
             .primitive_restart(false)
             .viewports(vec![viewport]) // NOTE: also sets scissor to cover whole viewport
-            .fragment_shader(frag_shader_module.main_entry_point(), ())
-            .depth_clamp(false)
+            .fragment_shader(frag_shader_module.main_entry_point(), specialization.fragment())
             // NOTE: there's an outcommented .rasterizer_discard() in Vulkano...
-            .polygon_mode_fill() // = default
-            .line_width(1.0) // = default
-            .cull_mode_back()
-            .front_face_clockwise()
-            // NOTE: no depth_bias here, but on pipeline::raster::Rasterization
-            .blend_pass_through() // = default
+            .depth_clamp(false);
+
+        // topology, polygon mode, culling, line width, depth and blend state
+        let pipeline = description.apply(builder)
             .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
             .build(device.clone())
-            .unwrap()
-        )
+            .map_err(Error::Pipeline)?;
+        Ok(Arc::new(pipeline))
+    }
+
+    /// Same as `create_graphics_pipeline`, but with per-object data in a second descriptor set.
+    fn create_object_uniforms_graphics_pipeline(
+        device: &Arc<Device>,
+        swap_chain_extent: [u32; 2],
+        render_pass: &Arc<RenderPassAbstract + Send + Sync>,
+        key: &PipelineKey,
+    ) -> Result<Arc<GraphicsPipelineAbstract + Send + Sync>, Error> {
+        let vert_shader_module = object_uniforms_vertex_shader::Shader::load(device.clone())
+            .map_err(Error::Shader)?;
+        let frag_shader_module = fragment_shader::Shader::load(device.clone())
+            .map_err(Error::Shader)?;
+
+        let dimensions = [swap_chain_extent[0] as f32, swap_chain_extent[1] as f32];
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions,
+            depth_range: 0.0 .. 1.0,
+        };
+
+        let builder = GraphicsPipeline::start()
+            .vertex_input_single_buffer::<Vertex>()
+            .vertex_shader(vert_shader_module.main_entry_point(), key.specialization.object_uniforms_vertex())
+            .primitive_restart(false)
+            .viewports(vec![viewport])
+            .fragment_shader(frag_shader_module.main_entry_point(), key.specialization.fragment())
+            .depth_clamp(false);
+
+        let pipeline = key.description.apply(builder)
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone())
+            .map_err(Error::Pipeline)?;
+        Ok(Arc::new(pipeline))
+    }
+
+    /// Same as `create_graphics_pipeline`, but with a second, per-instance vertex buffer.
+    fn create_instanced_graphics_pipeline(
+        device: &Arc<Device>,
+        swap_chain_extent: [u32; 2],
+        render_pass: &Arc<RenderPassAbstract + Send + Sync>,
+        key: &PipelineKey,
+    ) -> Result<Arc<GraphicsPipelineAbstract + Send + Sync>, Error> {
+        let vert_shader_module = instanced_vertex_shader::Shader::load(device.clone())
+            .map_err(Error::Shader)?;
+        let frag_shader_module = fragment_shader::Shader::load(device.clone())
+            .map_err(Error::Shader)?;
+
+        let dimensions = [swap_chain_extent[0] as f32, swap_chain_extent[1] as f32];
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions,
+            depth_range: 0.0 .. 1.0,
+        };
+
+        let builder = GraphicsPipeline::start()
+            // NOTE: binding 0 advances per vertex, binding 1 per instance
+            .vertex_input(OneVertexOneInstanceDefinition::<Vertex, InstanceData>::new())
+            .vertex_shader(vert_shader_module.main_entry_point(), key.specialization.instanced_vertex())
+            .primitive_restart(false)
+            .viewports(vec![viewport])
+            .fragment_shader(frag_shader_module.main_entry_point(), key.specialization.fragment())
+            .depth_clamp(false);
+
+        let pipeline = key.description.apply(builder)
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone())
+            .map_err(Error::Pipeline)?;
+        Ok(Arc::new(pipeline))
     }
 
     fn create_framebuffers(
+        device: &Arc<Device>,
         swap_chain_images: &[Arc<SwapchainImage<Window>>],
-        render_pass: &Arc<RenderPassAbstract + Send + Sync>
-    ) -> Vec<Arc<FramebufferAbstract + Send + Sync>> {
+        color_format: Format,
+        render_pass: &Arc<RenderPassAbstract + Send + Sync>,
+        samples: u32,
+    ) -> Result<Vec<Arc<FramebufferAbstract + Send + Sync>>, Error> {
+        let _span = logging::init_span(logging::SWAPCHAIN, "create_framebuffers");
         swap_chain_images.iter()
             .map(|image| {
-                let fba: Arc<FramebufferAbstract + Send + Sync> = Arc::new(Framebuffer::start(render_pass.clone())
-                    .add(image.clone()).unwrap()
-                    .build().unwrap());
-                fba
+                if samples > 1 {
+                    let multisampled = AttachmentImage::transient_multisampled(device.clone(), image.dimensions(),
+                        samples, color_format)
+                        .map_err(Error::Image)?;
+                    let framebuffer = Framebuffer::start(render_pass.clone())
+                        .add(multisampled)
+                        .and_then(|builder| builder.add(image.clone()))
+                        .and_then(|builder| builder.build())
+                        .map_err(Error::Framebuffer)?;
+                    let fba: Arc<FramebufferAbstract + Send + Sync> = Arc::new(framebuffer);
+                    return Ok(fba);
+                }
+
+                let framebuffer = Framebuffer::start(render_pass.clone())
+                    .add(image.clone())
+                    .and_then(|builder| builder.build())
+                    .map_err(Error::Framebuffer)?;
+                let fba: Arc<FramebufferAbstract + Send + Sync> = Arc::new(framebuffer);
+                Ok(fba)
             }
-        ).collect::<Vec<_>>()
+        ).collect()
+    }
+
+    /// NOTE: replaces the tutorial's `create_vertex_buffer` and `create_index_buffer`, which
+    /// create (and wait for) a separate buffer for the vertices and indices of each mesh.
+    fn create_mesh_buffers(graphics_queue: &Arc<Queue>) -> Result<Vec<UploadedMesh>, Error> {
+        let _span = logging::init_span(logging::DEVICE, "create_mesh_buffers");
+        let (meshes, report) = mesh_upload::upload_meshes(graphics_queue, &meshes())?;
+        info!(target: logging::DEVICE, "uploaded {} meshes ({} bytes) with {} device-local and {} staging allocation(s) \
+            and {} submission(s) (instead of {} of each)",
+            report.meshes, report.bytes, report.device_local_allocations, report.staging_allocations,
+            report.submissions, report.separate_allocations());
+        Ok(meshes)
+    }
+
+    /// NOTE: unlike the tutorial, the uniform buffers are device-local and only written by copying
+    /// from the upload ring at the start of each frame (see `record_uploads`)
+    fn create_uniform_buffers(
+        device: &Arc<Device>,
+        graphics_queue: &Arc<Queue>,
+        num_buffers: usize,
+    ) -> Result<Vec<Arc<DeviceLocalBuffer<UniformBufferObject>>>, Error> {
+        let _span = logging::init_span(logging::DEVICE, "create_uniform_buffers");
+        let mut buffers = Vec::new();
+
+        for _ in 0..num_buffers {
+            let buffer = DeviceLocalBuffer::new(
+                device.clone(),
+                BufferUsage::uniform_buffer_transfer_destination(),
+                Some(graphics_queue.family()),
+            ).map_err(Error::Memory)?;
+
+            buffers.push(buffer);
+        }
+
+        Ok(buffers)
+    }
+
+    /// One instance buffer per swap chain image, rewritten each frame (like the uniform buffers).
+    /// Empty if the scene doesn't use instancing.
+    fn create_instance_buffers(
+        device: &Arc<Device>,
+        graphics_queue: &Arc<Queue>,
+        num_buffers: usize,
+        scene: &Scene,
+    ) -> Result<Vec<Arc<DeviceLocalBuffer<[InstanceData]>>>, Error> {
+        let _span = logging::init_span(logging::DEVICE, "create_instance_buffers");
+        if scene.draw_mode != DrawMode::Instanced {
+            return Ok(vec![]);
+        }
+
+        let usage = BufferUsage {
+            vertex_buffer: true,
+            transfer_destination: true,
+            .. BufferUsage::none()
+        };
+        (0..num_buffers)
+            .map(|_| {
+                DeviceLocalBuffer::array(device.clone(), scene.instance_count, usage, Some(graphics_queue.family()))
+                    .map_err(Error::Memory)
+            })
+            .collect()
+    }
+
+    /// One buffer per swap chain image for all objects of the scene (empty if the scene doesn't use them).
+    fn create_object_uniform_buffers(
+        device: &Arc<Device>,
+        graphics_queue: &Arc<Queue>,
+        num_buffers: usize,
+        scene: &Scene,
+    ) -> Result<Vec<ObjectUniformBuffer<ObjectUniforms>>, Error> {
+        let _span = logging::init_span(logging::DEVICE, "create_object_uniform_buffers");
+        if scene.draw_mode != DrawMode::StorageBuffer {
+            return Ok(vec![]);
+        }
+
+        let buffers = (0..num_buffers)
+            .map(|_| ObjectUniformBuffer::new(device, graphics_queue, scene.objects.len()).map_err(Error::Memory))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        info!(target: logging::DEVICE, "{} objects: {} bytes each, {} buffer(s) of {} bytes",
+            scene.objects.len(), std::mem::size_of::<ObjectUniforms>(),
+            buffers.len(), std::mem::size_of::<ObjectUniforms>() * scene.objects.len());
+
+        Ok(buffers)
     }
 
-    fn create_vertex_buffer(graphics_queue: &Arc<Queue>) -> Arc<BufferAccess + Send + Sync> {
-        let (buffer, future) = ImmutableBuffer::from_iter(
-            vertices().iter().cloned(), BufferUsage::vertex_buffer(),
-            graphics_queue.clone())
-            .unwrap();
-        future.flush().unwrap();
-        buffer
+    /// For each swap chain image, the descriptor set (set 1) with all objects' entries.
+    fn create_object_descriptor_sets(
+        graphics_pipeline: &Arc<GraphicsPipelineAbstract + Send + Sync>,
+        object_uniform_buffers: &[ObjectUniformBuffer<ObjectUniforms>],
+    ) -> Result<Vec<Arc<DescriptorSet + Send + Sync>>, Error> {
+        let _span = logging::init_span(logging::PIPELINE, "create_object_descriptor_sets");
+        object_uniform_buffers.iter()
+            .map(|buffer| {
+                let set: Arc<DescriptorSet + Send + Sync> = Arc::new(
+                    PersistentDescriptorSet::start(graphics_pipeline.clone(), 1)
+                        .add_buffer(buffer.buffer().clone())
+                        .map_err(Error::descriptor_set)?
+                        .build()
+                        .map_err(Error::descriptor_set)?
+                );
+                Ok(set)
+            })
+            .collect()
     }
 
-    fn create_index_buffer(graphics_queue: &Arc<Queue>) -> Arc<TypedBufferAccess<Content=[u16]> + Send + Sync> {
-        let (buffer, future) = ImmutableBuffer::from_iter(
-            indices().iter().cloned(), BufferUsage::index_buffer(),
-            graphics_queue.clone())
-            .unwrap();
-        future.flush().unwrap();
-        buffer
+    fn create_descriptor_sets(
+        graphics_pipeline: &Arc<GraphicsPipelineAbstract + Send + Sync>,
+        uniform_buffers: &[Arc<DeviceLocalBuffer<UniformBufferObject>>],
+    ) -> Result<Vec<Arc<DescriptorSet + Send + Sync>>, Error> {
+        let _span = logging::init_span(logging::PIPELINE, "create_descriptor_sets");
+        uniform_buffers.iter()
+            .map(|uniform_buffer| {
+                let set: Arc<DescriptorSet + Send + Sync> = Arc::new(
+                    PersistentDescriptorSet::start(graphics_pipeline.clone(), 0)
+                        .add_buffer(uniform_buffer.clone())
+                        .map_err(Error::descriptor_set)?
+                        .build()
+                        .map_err(Error::descriptor_set)?
+                );
+                Ok(set)
+            })
+            .collect()
     }
 
-    fn create_command_buffers(&mut self) {
+    /// Prebuilds a command buffer per swap chain image (the tutorial's approach).
+    /// Nothing to do when recording per frame.
+    fn create_command_buffers(&mut self) -> Result<(), Error> {
+        let _span = logging::init_span(logging::FRAME, "create_command_buffers");
+        if self.recording_mode == RecordingMode::PerFrame {
+            self.command_buffers = vec![];
+            return Ok(());
+        }
+
+        let start = Instant::now();
         let queue_family = self.graphics_queue.family();
-        self.command_buffers = self.swap_chain_framebuffers.iter()
-            .map(|framebuffer| {
-                Arc::new(AutoCommandBufferBuilder::primary_simultaneous_use(self.device.clone(), queue_family)
-                    .unwrap()
-                    .begin_render_pass(framebuffer.clone(), false, vec![[0.0, 0.0, 0.0, 1.0].into()])
-                    .unwrap()
-                    .draw_indexed(self.graphics_pipeline.clone(), &DynamicState::none(),
-                        vec![self.vertex_buffer.clone()],
-                        self.index_buffer.clone(), (), ())
-                    .unwrap()
-                    .end_render_pass()
-                    .unwrap()
-                    .build()
-                    .unwrap())
+        self.command_buffers = (0..self.swap_chain_framebuffers.len())
+            .map(|i| {
+                let builder = AutoCommandBufferBuilder::primary_simultaneous_use(self.device.clone(), queue_family)
+                    .map_err(Error::command_buffer)?;
+                Ok(Arc::new(self.record_command_buffer(i, builder)?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        self.frame_stats.add_recording(start.elapsed());
+        Ok(())
+    }
+
+    /// Records the render pass for a swap chain image with the current scene state.
+    fn record_command_buffer(&self, i: usize, builder: AutoCommandBufferBuilder) -> Result<AutoCommandBuffer, Error> {
+        let draw_calls = self.draw_calls(i);
+        let use_secondary = self.recording_threads > 1;
+        let clear_values = if self.msaa_samples > 1 {
+            // the swap chain image is only written by the resolve
+            vec![[0.0, 0.0, 0.0, 1.0].into(), ClearValue::None]
+        } else {
+            vec![[0.0, 0.0, 0.0, 1.0].into()]
+        };
+        let mut builder = builder;
+        if let Some(ref gpu_timer) = self.gpu_timer {
+            // NOTE: unsafe in vulkano for the same reason as below; these only write timestamps
+            builder = unsafe { builder.execute_commands(gpu_timer.begin_commands(i)) }
+                .map_err(Error::command_buffer)?;
+        }
+        builder = builder
+            .begin_render_pass(self.swap_chain_framebuffers[i].clone(), use_secondary, clear_values)
+            .map_err(Error::command_buffer)?;
+
+        if use_secondary {
+            for secondary in self.record_secondary_command_buffers(draw_calls)? {
+                // NOTE: unsafe in vulkano because it doesn't track the secondary command buffer's
+                // resources, so it can't synchronize them with other work on the GPU. The only writes
+                // they depend on are the copies of `record_uploads`, which `draw_frame` makes visible
+                // with a semaphore between the two submissions.
+                builder = unsafe { builder.execute_commands(secondary) }
+                    .map_err(Error::command_buffer)?;
+            }
+        } else {
+            builder = record_draw_calls(builder, &self.graphics_pipeline, &draw_calls)
+                .map_err(Error::CommandBuffer)?;
+        }
+
+        builder = builder
+            .end_render_pass()
+            .map_err(Error::command_buffer)?;
+        if let Some(ref gpu_timer) = self.gpu_timer {
+            builder = unsafe { builder.execute_commands(gpu_timer.end_commands(i)) }
+                .map_err(Error::command_buffer)?;
+        }
+        builder
+            .build()
+            .map_err(Error::command_buffer)
+    }
+
+    /// Everything to draw into the image, with the buffers and descriptor sets of that image.
+    fn draw_calls(&self, i: usize) -> Vec<DrawCall> {
+        let descriptor_set = &self.descriptor_sets[i];
+
+        // all instances in a single draw call
+        if let Some(instance_buffer) = self.instance_buffers.get(i) {
+            let instance_buffer: Arc<BufferAccess + Send + Sync> = instance_buffer.clone();
+            let mesh = &self.meshes[0];
+            return vec![DrawCall {
+                vertex_buffers: vec![mesh.vertex_buffer.clone(), instance_buffer],
+                index_buffer: mesh.index_buffer.clone(),
+                descriptor_sets: vec![descriptor_set.clone()],
+                push_constants: DrawPushConstants::None,
+            }];
+        }
+
+        match self.scene.draw_mode {
+            // one draw call per object, only the push constants and mesh slices differ
+            DrawMode::PushConstants => self.scene.objects.iter()
+                .map(|object| {
+                    let mesh = &self.meshes[object.mesh % self.meshes.len()];
+                    DrawCall {
+                        vertex_buffers: vec![mesh.vertex_buffer.clone()],
+                        index_buffer: mesh.index_buffer.clone(),
+                        descriptor_sets: vec![descriptor_set.clone()],
+                        push_constants: DrawPushConstants::Object(push_constants(object)),
+                    }
+                })
+                .collect(),
+            // one draw call per object, only the index of the object's entry and mesh slices differ
+            DrawMode::StorageBuffer => self.scene.objects.iter()
+                .enumerate()
+                .map(|(index, object)| {
+                    let mesh = &self.meshes[object.mesh % self.meshes.len()];
+                    DrawCall {
+                        vertex_buffers: vec![mesh.vertex_buffer.clone()],
+                        index_buffer: mesh.index_buffer.clone(),
+                        descriptor_sets: vec![descriptor_set.clone(), self.object_descriptor_sets[i].clone()],
+                        push_constants: DrawPushConstants::ObjectIndex(
+                            object_uniforms_vertex_shader::ty::PushConstants { object_index: index as u32 }),
+                    }
+                })
+                .collect(),
+            DrawMode::Instanced => vec![],
+        }
+    }
+
+    /// Splits the draw calls into one chunk per thread, each recorded into a secondary command buffer.
+    /// NOTE: the threads are spawned for each recording, which is part of the measured time
+    fn record_secondary_command_buffers(&self, draw_calls: Vec<DrawCall>) -> Result<Vec<AutoCommandBuffer>, Error> {
+        let chunk_size = ((draw_calls.len() + self.recording_threads - 1) / self.recording_threads).max(1);
+        let queue_family_id = self.graphics_queue.family().id();
+
+        let threads: Vec<_> = draw_calls.chunks(chunk_size)
+            .map(|chunk| {
+                let chunk = chunk.to_vec();
+                let device = self.device.clone();
+                let render_pass = self.render_pass.clone();
+                let graphics_pipeline = self.graphics_pipeline.clone();
+                // NOTE: returns vulkano's error message, like `record_draw_calls`
+                thread::spawn(move || {
+                    let queue_family = device.physical_device().queue_family_by_id(queue_family_id).unwrap();
+                    let builder = AutoCommandBufferBuilder::secondary_graphics(device.clone(), queue_family,
+                        Subpass::from(render_pass, 0).unwrap())
+                        .map_err(|err| err.to_string())?;
+                    record_draw_calls(builder, &graphics_pipeline, &chunk)?
+                        .build()
+                        .map_err(|err| err.to_string())
+                })
             })
             .collect();
+
+        threads.into_iter()
+            .map(|thread| thread.join()
+                .map_err(|_| Error::CommandBuffer("recording thread panicked".to_string()))?
+                .map_err(Error::CommandBuffer))
+            .collect()
+    }
+
+    fn create_gpu_timer(
+        instance: &Arc<Instance>,
+        physical_device_index: usize,
+        device: &Arc<Device>,
+        graphics_queue: &Arc<Queue>,
+        image_count: usize,
+    ) -> Option<GpuTimer> {
+        let _span = logging::init_span(logging::DEVICE, "create_gpu_timer");
+        let physical_device = PhysicalDevice::from_index(&instance, physical_device_index).unwrap();
+        let gpu_timer = GpuTimer::new(physical_device, device, graphics_queue, image_count);
+        if gpu_timer.is_none() {
+            info!(target: logging::DEVICE, "Timestamp queries not supported, GPU timing disabled");
+        }
+        gpu_timer
     }
 
     fn create_sync_objects(device: &Arc<Device>) -> Box<GpuFuture> {
@@ -509,7 +1345,7 @@ impl HelloTriangleApplication {
                 indices.graphics_family = i as i32;
             }
 
-            if surface.is_supported(queue_family).unwrap() {
+            if surface.is_supported(queue_family).unwrap_or(false) {
                 indices.present_family = i as i32;
             }
 
@@ -525,7 +1361,8 @@ impl HelloTriangleApplication {
         instance: &Arc<Instance>,
         surface: &Arc<Surface<Window>>,
         physical_device_index: usize,
-    ) -> (Arc<Device>, Arc<Queue>, Arc<Queue>) {
+    ) -> Result<(Arc<Device>, Arc<Queue>, Arc<Queue>), Error> {
+        let _span = logging::init_span(logging::DEVICE, "create_logical_device");
         let physical_device = PhysicalDevice::from_index(&instance, physical_device_index).unwrap();
         let indices = Self::find_queue_families(&surface, &physical_device);
 
@@ -539,104 +1376,682 @@ impl HelloTriangleApplication {
         });
 
         // NOTE: the tutorial recommends passing the validation layers as well
-        // for legacy reasons (if ENABLE_VALIDATION_LAYERS is true). Vulkano handles that
+        // for legacy reasons (if validation is enabled). Vulkano handles that
         // for us internally.
 
-        let (device, mut queues) = Device::new(physical_device, &Features::none(),
+        // NOTE: only needed for the wireframe mode, so we don't require it
+        let features = Features {
+            fill_mode_non_solid: physical_device.supported_features().fill_mode_non_solid,
+            .. Features::none()
+        };
+
+        let (device, mut queues) = Device::new(physical_device, &features,
             &device_extensions(), queue_families)
-            .expect("failed to create logical device!");
+            .map_err(Error::Device)?;
 
         let graphics_queue = queues.next().unwrap();
         let present_queue = queues.next().unwrap_or_else(|| graphics_queue.clone());
 
-        (device, graphics_queue, present_queue)
+        Ok((device, graphics_queue, present_queue))
     }
 
-    fn create_surface(instance: &Arc<Instance>) -> (EventsLoop, Arc<Surface<Window>>) {
+    fn create_surface(instance: &Arc<Instance>, config: &Config)
+        -> Result<(EventsLoop, Arc<Surface<Window>>), Error>
+    {
+        let _span = logging::init_span(logging::SWAPCHAIN, "create_surface");
         let events_loop = EventsLoop::new();
-        let surface = WindowBuilder::new()
-            .with_title("Vulkan")
-            .with_dimensions(LogicalSize::new(f64::from(WIDTH), f64::from(HEIGHT)))
-            .build_vk_surface(&events_loop, instance.clone())
-            .expect("failed to create window surface!");
-        (events_loop, surface)
+        let surface = Self::create_window_surface(&events_loop, instance, config)?;
+        Ok((events_loop, surface))
+    }
+
+    /// NOTE: `headless` only hides the window - the swap chain still needs a surface to present to
+    fn create_window_surface(events_loop: &EventsLoop, instance: &Arc<Instance>, config: &Config)
+        -> Result<Arc<Surface<Window>>, Error>
+    {
+        WindowBuilder::new()
+            .with_title(config.window.title.clone())
+            .with_dimensions(LogicalSize::new(f64::from(config.window.width), f64::from(config.window.height)))
+            .with_visibility(!config.headless)
+            .build_vk_surface(events_loop, instance.clone())
+            .map_err(Error::Surface)
     }
 
     #[allow(unused)]
-    fn main_loop(&mut self) {
+    fn main_loop(&mut self) -> Result<(), Error> {
+        let mut redraw_requested = true;
         loop {
-            self.draw_frame();
+            if redraw_requested {
+                self.draw_frame()?;
+                if let Some(ref mut frame_limiter) = self.frame_limiter {
+                    frame_limiter.wait();
+                }
+            }
+
+            if self.benchmark.as_ref().map_or(false, |benchmark| benchmark.is_finished()) {
+                return self.finish_benchmark();
+            }
 
             let mut done = false;
-            self.events_loop.poll_events(|ev| {
-                if let Event::WindowEvent { event: WindowEvent::CloseRequested, .. } = ev {
-                    done = true
+            let wait_for_events = self.redraw_mode == RedrawMode::OnDemand && !self.is_animating();
+            redraw_requested = !wait_for_events;
+            self.input.begin_frame();
+            let input = &mut self.input;
+            let mut handle_event = |ev: Event| {
+                if let Event::WindowEvent { event, .. } = ev {
+                    input.handle_event(&event);
+                    redraw_requested |= redraw::needs_redraw(&event);
+                    if let WindowEvent::CloseRequested = event {
+                        done = true;
+                    }
                 }
-            });
+            };
+            if wait_for_events {
+                // block until the first event, then handle the rest without waiting
+                self.events_loop.run_forever(|ev| {
+                    handle_event(ev);
+                    ControlFlow::Break
+                });
+            }
+            self.events_loop.poll_events(handle_event);
             if done {
-                return;
+                info!(target: logging::SUMMARY, "upload ring: {}", self.upload_stats);
+                self.log_frame_stats();
+                if let Some(ref csv_path) = self.csv_path {
+                    match self.frame_times.write_csv(csv_path) {
+                        Ok(()) => info!(target: logging::SUMMARY, "frame times written to {}", csv_path),
+                        Err(err) => error!(target: logging::FRAME, "failed to write {}: {}", csv_path, err),
+                    }
+                }
+                return Ok(());
+            }
+            let actions: Vec<Action> = self.bindings.triggered(&self.input).collect();
+            for action in actions {
+                self.handle_action(action)?;
             }
         }
     }
 
-    fn draw_frame(&mut self) {
+    /// Frames change without any events.
+    fn is_animating(&self) -> bool {
+        self.time.is_animating() || self.recreate_swap_chain || self.recovery.pending().is_some()
+            || self.camera.is_moving(&self.input, &self.bindings)
+    }
+
+    fn handle_action(&mut self, action: Action) -> Result<(), Error> {
+        match action {
+            Action::ToggleWireframe => self.toggle_wireframe()?,
+            Action::ToggleVertexColor => self.toggle_vertex_color()?,
+            Action::CycleColorLevels => self.cycle_color_levels()?,
+            Action::ToggleRecordingMode => self.toggle_recording_mode()?,
+            Action::ToggleRecordingThreads => self.toggle_recording_threads()?,
+            Action::Pause => self.time.toggle_pause(),
+            Action::Step => self.time.step(),
+            Action::ToggleCameraMode => self.camera.toggle_mode(),
+            Action::CycleProjection => {
+                self.camera.cycle_projection();
+                info!(target: logging::FRAME, "projection: {:?}", self.camera.projection);
+            },
+            Action::ResetCamera => self.camera.reset(),
+            Action::Faster => {
+                self.time.faster();
+                info!(target: logging::FRAME, "animation speed: {}x", self.time.speed());
+            },
+            Action::Slower => {
+                self.time.slower();
+                info!(target: logging::FRAME, "animation speed: {}x", self.time.speed());
+            },
+            // continuous, see `Camera::update`
+            Action::MoveForward | Action::MoveBackward | Action::MoveLeft | Action::MoveRight
+                | Action::MoveDown | Action::MoveUp => (),
+        }
+        Ok(())
+    }
+
+    fn draw_frame(&mut self) -> Result<(), Error> {
+        let _frame_span = logging::frame_span("draw_frame");
+        let frame_start = Instant::now();
+        self.frame_times.tick();
+        self.camera.update(&self.input, &self.bindings);
         self.previous_frame_end.as_mut().unwrap().cleanup_finished();
 
+        if let Some(lost) = self.recovery.pending() {
+            return self.recover(lost);
+        }
+
         if self.recreate_swap_chain {
-            self.recreate_swap_chain();
-            self.recreate_swap_chain = false;
+            match self.recreate_swap_chain() {
+                Ok(()) => self.recreate_swap_chain = false,
+                // e.g. while the window is minimized; try again in the next frame
+                Err(Error::Swapchain(SwapchainCreationError::UnsupportedDimensions)) => return Ok(()),
+                Err(err) => return Err(err),
+            }
         }
 
+        let acquire_span = logging::frame_span("acquire");
+        let acquire_start = Instant::now();
         let (image_index, acquire_future) = match acquire_next_image(self.swap_chain.clone(), None) {
             Ok(r) => r,
             Err(AcquireError::OutOfDate) => {
                 self.recreate_swap_chain = true;
-                return;
+                return Ok(());
+            },
+            Err(AcquireError::DeviceLost) => {
+                self.recovery.lost(Lost::Device);
+                return Ok(());
+            },
+            Err(AcquireError::SurfaceLost) => {
+                self.recovery.lost(Lost::Surface);
+                return Ok(());
             },
-            Err(err) => panic!("{:?}", err)
+            Err(err) => return Err(Error::Acquire(err)),
         };
 
-        let command_buffer = self.command_buffers[image_index].clone();
+        let acquire_time = acquire_start.elapsed();
+        drop(acquire_span);
+
+        let upload_span = logging::frame_span("uploads");
+        let upload_command_buffer = self.record_uploads(image_index)?;
+        drop(upload_span);
+
+        let command_buffer = match self.recording_mode {
+            RecordingMode::Prebuilt => self.command_buffers[image_index].clone(),
+            RecordingMode::PerFrame => {
+                let _span = logging::frame_span("record command buffer");
+                let start = Instant::now();
+                let builder = AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(),
+                    self.graphics_queue.family())
+                    .map_err(Error::command_buffer)?;
+                let command_buffer = Arc::new(self.record_command_buffer(image_index, builder)?);
+                self.frame_stats.add_recording(start.elapsed());
+                command_buffer
+            }
+        };
 
+        if let Some(ref mut gpu_timer) = self.gpu_timer {
+            gpu_timer.begin_frame(image_index);
+        }
+
+        let present_span = logging::frame_span("submit and present");
+        let present_start = Instant::now();
+        // NOTE: the uploads are a separate submission that signals a semaphore the draws wait on.
+        // Vulkano can't see what the draws read when they are recorded into secondary command
+        // buffers, so it wouldn't make the copies into the uniform, instance and object buffers
+        // visible itself.
         let future = self.previous_frame_end.take().unwrap()
             .join(acquire_future)
+            .then_execute(self.graphics_queue.clone(), upload_command_buffer)
+            .map_err(Error::Execute)?
+            .then_signal_semaphore()
             .then_execute(self.graphics_queue.clone(), command_buffer)
-            .unwrap()
+            .map_err(Error::Execute)?
             .then_swapchain_present(self.present_queue.clone(), self.swap_chain.clone(), image_index)
             .then_signal_fence_and_flush();
+        let present_time = present_start.elapsed();
+        drop(present_span);
 
         match future {
             Ok(future) => {
                 self.previous_frame_end = Some(Box::new(future) as Box<_>);
+                // NOTE: only here, so frames that are skipped or not presented don't move the animation on
+                self.time.tick();
+                self.recovery.frame_presented();
+                if let Some(ref mut gpu_timer) = self.gpu_timer {
+                    gpu_timer.frame_submitted(image_index);
+                }
             }
             Err(vulkano::sync::FlushError::OutOfDate) => {
                 self.recreate_swap_chain = true;
                 self.previous_frame_end
                     = Some(Box::new(vulkano::sync::now(self.device.clone())) as Box<_>);
             }
-            Err(e) => {
-                println!("{:?}", e);
+            Err(vulkano::sync::FlushError::DeviceLost) => {
+                self.recovery.lost(Lost::Device);
+                self.previous_frame_end
+                    = Some(Box::new(vulkano::sync::now(self.device.clone())) as Box<_>);
+            }
+            Err(vulkano::sync::FlushError::SurfaceLost) => {
+                self.recovery.lost(Lost::Surface);
                 self.previous_frame_end
                     = Some(Box::new(vulkano::sync::now(self.device.clone())) as Box<_>);
             }
+            Err(e) => return Err(Error::Present(e)),
         }
+
+        self.frame_stats.add_frame(frame_start.elapsed());
+        if let Some(ref mut benchmark) = self.benchmark {
+            benchmark.add_frame(FrameRecord {
+                cpu: milliseconds(frame_start.elapsed()),
+                gpu: self.gpu_timer.as_mut().and_then(|gpu_timer| gpu_timer.take_latest()),
+                acquire: milliseconds(acquire_time),
+                present: milliseconds(present_time),
+            });
+        }
+        self.report_stats();
+        Ok(())
     }
 
-    fn recreate_swap_chain(&mut self) {
+    /// Once per second: frame times and rolling averages in the window title (and the `frame` log at debug level).
+    fn report_stats(&mut self) {
+        if self.last_stats_report.elapsed() < Duration::from_secs(1) {
+            return;
+        }
+        self.last_stats_report = Instant::now();
+
+        let mut title = self.config.window.title.clone();
+        if let Some(summary) = self.frame_times.summary() {
+            title += &format!(" - {}", summary);
+        }
+        if let Some(gpu_time) = self.gpu_timer.as_ref().and_then(|gpu_timer| gpu_timer.average()) {
+            title += &format!(" - GPU {:.3} ms", gpu_time);
+        }
+        if self.time.is_paused() {
+            title += " - paused";
+        }
+        debug!(target: logging::FRAME, "{}", title);
+        self.surface.window().set_title(&title);
+    }
+
+    fn update_uniform_buffer(seconds: f32, camera: &Camera) -> UniformBufferObject {
+        let model = Matrix4::from_angle_z(Rad::from(Deg(seconds * 180.0)));
+
+        // NOTE: unlike `cgmath::perspective`, the projection is already in Vulkan's clip space (no Y flip needed)
+        UniformBufferObject { model, view: camera.view(), proj: camera.projection() }
+    }
+
+    /// Writes this frame's uniforms (and instance or object data) into the upload rings and records
+    /// the copies into the buffers of the swap chain image. Executed before the (prebuilt)
+    /// command buffer of the image.
+    fn record_uploads(&mut self, image_index: usize) -> Result<AutoCommandBuffer, Error> {
+        let seconds = self.time.seconds();
+        let uniform_buffer_object = Self::update_uniform_buffer(seconds, &self.camera);
+
+        let mut builder = AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(),
+            self.graphics_queue.family())
+            .map_err(Error::command_buffer)?;
+
+        let source = self.uniform_ring.upload(uniform_buffer_object, &mut self.upload_stats)
+            .map_err(Error::Memory)?;
+        builder = builder.copy_buffer(source, self.uniform_buffers[image_index].clone())
+            .map_err(Error::command_buffer)?;
+
+        if let Some(instance_buffer) = self.instance_buffers.get(image_index) {
+            let instances = animate_instances(self.scene.instance_count, seconds)
+                .map(|(instance_offset, instance_scale, instance_color)|
+                    InstanceData { instance_offset, instance_scale, instance_color });
+            let source = self.instance_ring.upload_iter(instances, &mut self.upload_stats)
+                .map_err(Error::Memory)?;
+            builder = builder.copy_buffer(source, instance_buffer.clone())
+                .map_err(Error::command_buffer)?;
+        }
+
+        if let Some(object_uniform_buffer) = self.object_uniform_buffers.get(image_index) {
+            let source = self.object_ring.upload_iter(object_uniforms(&self.scene, seconds), &mut self.upload_stats)
+                .map_err(Error::Memory)?;
+            builder = builder.copy_buffer(source, object_uniform_buffer.buffer().clone())
+                .map_err(Error::command_buffer)?;
+        }
+
+        self.upload_stats.end_frame();
+        builder.build().map_err(Error::command_buffer)
+    }
+
+    fn finish_benchmark(&self) -> Result<(), Error> {
+        let benchmark = self.benchmark.as_ref().unwrap();
+        benchmark.write_report(&self.device_info())
+            .map_err(|err| Error::BenchmarkReport(benchmark.config.report_path.clone(), err))?;
+        info!(target: logging::SUMMARY, "benchmark report written to {}", benchmark.config.report_path);
+        Ok(())
+    }
+
+    fn device_info(&self) -> DeviceInfo {
+        let physical_device = PhysicalDevice::from_index(&self.instance, self.physical_device_index).unwrap();
+        let api_version = physical_device.api_version();
+        DeviceInfo {
+            name: physical_device.name(),
+            device_type: format!("{:?}", physical_device.ty()),
+            vendor_id: physical_device.pci_vendor_id(),
+            device_id: physical_device.pci_device_id(),
+            api_version: api_version::to_string(api_version),
+            negotiated_api_version: api_version::to_string(self.api_version),
+            driver_version: benchmark::driver_version(physical_device.pci_vendor_id(),
+                physical_device.driver_version()),
+            driver_version_raw: physical_device.driver_version(),
+        }
+    }
+
+    fn toggle_wireframe(&mut self) -> Result<(), Error> {
+        if !self.device.enabled_features().fill_mode_non_solid {
+            warn!(target: logging::PIPELINE, "Wireframe mode not available (fill_mode_non_solid not supported)");
+            return Ok(());
+        }
+
+        self.pipeline_description = if self.pipeline_description.polygon_mode == PolygonMode::Fill {
+            self.configured_pipeline_description.wireframe()
+        } else {
+            self.configured_pipeline_description.clone()
+        };
+        self.update_graphics_pipeline()
+    }
+
+    fn toggle_vertex_color(&mut self) -> Result<(), Error> {
+        self.shader_specialization.use_vertex_color = !self.shader_specialization.use_vertex_color;
+        self.update_graphics_pipeline()
+    }
+
+    /// Posterization off, then with 2, 4 and 8 levels per color channel.
+    fn cycle_color_levels(&mut self) -> Result<(), Error> {
+        self.shader_specialization.color_levels = match self.shader_specialization.color_levels {
+            0 => 2,
+            levels if levels < 8 => levels * 2,
+            _ => 0,
+        };
+        info!(target: logging::PIPELINE, "color levels: {}", self.shader_specialization.color_levels);
+        self.update_graphics_pipeline()
+    }
+
+    /// Logs the stats of the current mode, so both can be compared, and starts over with the other one.
+    fn toggle_recording_mode(&mut self) -> Result<(), Error> {
+        self.log_frame_stats();
+        self.frame_stats = FrameStats::default();
+
+        self.recording_mode = match self.recording_mode {
+            RecordingMode::Prebuilt => RecordingMode::PerFrame,
+            RecordingMode::PerFrame => RecordingMode::Prebuilt,
+        };
+        self.create_command_buffers()
+    }
+
+    /// Switches between single-threaded recording and the configured number of threads.
+    fn toggle_recording_threads(&mut self) -> Result<(), Error> {
+        self.log_frame_stats();
+        self.frame_stats = FrameStats::default();
+
+        self.recording_threads = if self.recording_threads == 1 { self.configured_recording_threads } else { 1 };
+        self.create_command_buffers()
+    }
+
+    fn log_frame_stats(&self) {
+        info!(target: logging::SUMMARY, "{:?} recording on {} thread(s): {}",
+            self.recording_mode, self.recording_threads, self.frame_stats);
+    }
+
+    /// Like the tutorial's `cleanup`: waits until the device is idle, then destroys everything
+    /// in the reverse order of creation. Debug builds report Vulkan objects that outlive it.
+    fn shutdown(self) {
+        if !self.recovery.log().is_empty() {
+            info!(target: logging::DEVICE, "recovery log:");
+            for entry in self.recovery.log() {
+                info!(target: logging::DEVICE, "  {}", entry);
+            }
+        }
+
+        let mut tracker = ResourceTracker::default();
+        if cfg!(debug_assertions) {
+            self.track_resources(&mut tracker);
+            debug!(target: logging::DEVICE, "live at shutdown: {}", tracker.live_counts());
+        }
+
+        // NOTE: nothing else submits work at this point (the recording threads are joined every frame)
+        if let Err(err) = unsafe { self.device.wait() } {
+            error!(target: logging::DEVICE, "failed to wait for the device to be idle: {}", err);
+        }
+
+        let HelloTriangleApplication {
+            instance, debug_callback, events_loop, surface, device, graphics_queue, present_queue,
+            swap_chain, swap_chain_images, render_pass, pipeline_cache, graphics_pipeline,
+            swap_chain_framebuffers, meshes, uniform_buffers, descriptor_sets, instance_buffers,
+            object_uniform_buffers, object_descriptor_sets, uniform_ring, instance_ring, object_ring,
+            command_buffers, gpu_timer, previous_frame_end, ..
+        } = self;
+        drop(previous_frame_end);
+        drop(gpu_timer);
+        drop(command_buffers);
+        drop(object_descriptor_sets);
+        drop(descriptor_sets);
+        drop(uniform_ring);
+        drop(instance_ring);
+        drop(object_ring);
+        drop(object_uniform_buffers);
+        drop(instance_buffers);
+        drop(uniform_buffers);
+        drop(meshes);
+        drop(swap_chain_framebuffers);
+        drop(graphics_pipeline);
+        drop(pipeline_cache);
+        drop(render_pass);
+        drop(swap_chain_images);
+        drop(swap_chain);
+        drop(graphics_queue);
+        drop(present_queue);
+        drop(device);
+        drop(surface);
+        drop(events_loop);
+        drop(debug_callback);
+        drop(instance);
+
+        if cfg!(debug_assertions) {
+            let leaked = tracker.live_counts();
+            if leaked.total() > 0 {
+                warn!(target: logging::DEVICE, "still alive after shutdown: {}", leaked);
+            }
+        }
+    }
+
+    fn track_resources(&self, tracker: &mut ResourceTracker) {
+        for mesh in &self.meshes {
+            tracker.track(ResourceKind::Buffer, &mesh.vertex_buffer);
+            tracker.track(ResourceKind::Buffer, &mesh.index_buffer);
+        }
+        for buffer in &self.uniform_buffers {
+            tracker.track(ResourceKind::Buffer, buffer);
+        }
+        for buffer in &self.instance_buffers {
+            tracker.track(ResourceKind::Buffer, buffer);
+        }
+        for buffer in &self.object_uniform_buffers {
+            tracker.track(ResourceKind::Buffer, buffer.buffer());
+        }
+        for image in &self.swap_chain_images {
+            tracker.track(ResourceKind::Image, image);
+        }
+        // `graphics_pipeline` is one of them
+        for pipeline in self.pipeline_cache.pipelines() {
+            tracker.track(ResourceKind::Pipeline, pipeline);
+        }
+        for set in self.descriptor_sets.iter().chain(&self.object_descriptor_sets) {
+            tracker.track(ResourceKind::DescriptorSet, set);
+        }
+    }
+
+    /// Switches to the pipeline for the current description and specialization constants
+    /// and records the command buffers again.
+    fn update_graphics_pipeline(&mut self) -> Result<(), Error> {
+        self.select_graphics_pipeline()?;
+        // NOTE: the descriptor sets stay valid: all pipelines of a draw mode have the same layout
+        self.create_command_buffers()
+    }
+
+    /// Builds the pipeline for the current state only if it isn't cached yet.
+    fn select_graphics_pipeline(&mut self) -> Result<(), Error> {
+        let key = PipelineKey {
+            draw_mode: self.scene.draw_mode,
+            description: self.pipeline_description.clone(),
+            specialization: self.shader_specialization,
+        };
+        let device = &self.device;
+        let dimensions = self.swap_chain.dimensions();
+        let render_pass = &self.render_pass;
+        self.graphics_pipeline = self.pipeline_cache.get_or_create(&key,
+            |key| Self::create_graphics_pipeline(device, dimensions, render_pass, key))?;
+        Ok(())
+    }
+
+    fn recreate_swap_chain(&mut self) -> Result<(), Error> {
+        let old_swapchain = self.swap_chain.clone();
+        self.rebuild_swap_chain(Some(old_swapchain))
+    }
+
+    /// Rebuilds the swap chain and everything that depends on it (or on the number of images).
+    fn rebuild_swap_chain(&mut self, old_swapchain: Option<Arc<Swapchain<Window>>>) -> Result<(), Error> {
         let (swap_chain, images) = Self::create_swap_chain(&self.instance, &self.surface, self.physical_device_index,
-            &self.device, &self.graphics_queue, &self.present_queue, Some(self.swap_chain.clone()));
+            &self.device, &self.graphics_queue, &self.present_queue, &self.config, old_swapchain)?;
         self.swap_chain = swap_chain;
         self.swap_chain_images = images;
+        self.camera.set_dimensions(self.swap_chain.dimensions());
+
+        self.render_pass = Self::create_render_pass(&self.device, self.swap_chain.format(), self.msaa_samples)?;
+        self.swap_chain_framebuffers = Self::create_framebuffers(&self.device, &self.swap_chain_images,
+            self.swap_chain.format(), &self.render_pass, self.msaa_samples)?;
+        self.uniform_buffers = Self::create_uniform_buffers(&self.device, &self.graphics_queue,
+            self.swap_chain_images.len())?;
+        self.instance_buffers = Self::create_instance_buffers(&self.device, &self.graphics_queue,
+            self.swap_chain_images.len(), &self.scene)?;
+        self.object_uniform_buffers = Self::create_object_uniform_buffers(&self.device, &self.graphics_queue,
+            self.swap_chain_images.len(), &self.scene)?;
+        // the number of images might have changed
+        if self.gpu_timer.is_some() {
+            self.gpu_timer = None;
+            self.gpu_timer = Self::create_gpu_timer(&self.instance, self.physical_device_index, &self.device,
+                &self.graphics_queue, self.swap_chain_images.len());
+        }
 
-        self.render_pass = Self::create_render_pass(&self.device, self.swap_chain.format());
-        self.graphics_pipeline = Self::create_graphics_pipeline(&self.device, self.swap_chain.dimensions(),
-            &self.render_pass);
-        self.swap_chain_framebuffers = Self::create_framebuffers(&self.swap_chain_images, &self.render_pass);
-        self.create_command_buffers();
+        // all cached pipelines were built for the old render pass and viewport
+        self.pipeline_cache.clear();
+        self.select_graphics_pipeline()?;
+        self.descriptor_sets = Self::create_descriptor_sets(&self.graphics_pipeline, &self.uniform_buffers)?;
+        self.object_descriptor_sets = Self::create_object_descriptor_sets(&self.graphics_pipeline,
+            &self.object_uniform_buffers)?;
+        self.create_command_buffers()
+    }
+
+    /// Rebuilds what was lost, skipping the frame. Fails once out of attempts.
+    fn recover(&mut self, lost: Lost) -> Result<(), Error> {
+        if !self.recovery.can_retry() {
+            return Err(match lost {
+                Lost::Device => Error::DeviceLost,
+                Lost::Surface => Error::SurfaceLost,
+            });
+        }
+
+        let _span = logging::init_span(logging::DEVICE, "recover");
+        let start = Instant::now();
+        let result = match lost {
+            Lost::Device => self.recreate_device(),
+            Lost::Surface => self.recreate_surface(),
+        };
+        self.recovery.record(lost, result, start.elapsed());
+        Ok(())
+    }
+
+    /// Closes the window and opens a new one with a new surface, at the same size and position.
+    /// NOTE: the old swap chain keeps the native window in use until everything created from it
+    /// is dropped, which is only after the new swap chain has been created. A second surface for
+    /// the same window would fail to get a swap chain then.
+    fn replace_window(&mut self) -> Result<(), Error> {
+        warn!(target: logging::SWAPCHAIN, "replacing the window to get a new surface");
+        let size = self.surface.window().get_inner_size();
+        let position = self.surface.window().get_position();
+        let surface = Self::create_window_surface(&self.events_loop, &self.instance, &self.config)?;
+        if let Some(size) = size {
+            surface.window().set_inner_size(size);
+        }
+        if let Some(position) = position {
+            surface.window().set_position(position);
+        }
+        self.surface = surface;
+        Ok(())
+    }
+
+    /// The old swap chain belongs to the lost surface, so it can't be passed on.
+    fn recreate_surface(&mut self) -> Result<(), Error> {
+        self.replace_window()?;
+        self.recreate_swap_chain = false;
+        self.rebuild_swap_chain(None)
+    }
+
+    /// Rebuilds everything created from the device. The old swap chain can't be passed on to
+    /// another device, so this starts over with a new window surface as well.
+    fn recreate_device(&mut self) -> Result<(), Error> {
+        self.replace_window()?;
+        self.physical_device_index = Self::pick_physical_device(&self.instance, &self.surface, &self.config)?;
+        self.api_version = Self::negotiate_api_version(&self.instance, self.physical_device_index, &self.config);
+        self.msaa_samples = Self::choose_sample_count(&self.instance, self.physical_device_index,
+            self.config.msaa_samples);
+        let (device, graphics_queue, present_queue) = Self::create_logical_device(
+            &self.instance, &self.surface, self.physical_device_index)?;
+        self.device = device;
+        self.graphics_queue = graphics_queue;
+        self.present_queue = present_queue;
+        self.previous_frame_end = Some(Self::create_sync_objects(&self.device));
+
+        self.meshes = Self::create_mesh_buffers(&self.graphics_queue)?;
+        self.uniform_ring = UploadRing::new(&self.device);
+        self.instance_ring = UploadRing::new(&self.device);
+        self.object_ring = UploadRing::new(&self.device);
+
+        // also recreates the GPU timer, pipelines, descriptor sets and command buffers
+        self.recreate_swap_chain = false;
+        self.rebuild_swap_chain(None)
     }
 }
 
+/// Compares the shader interface against `Vertex` and `UniformBufferObject`.
+/// Doesn't need a GPU, so it can also be called from a unit test.
+fn check_shader_interface(shader_dir: &str) -> Result<Vec<reflection::Mismatch>, Error> {
+    let interface = reflection::compile_glsl(&format!("{}/shader.vert", shader_dir),
+        shaderc::ShaderKind::Vertex)
+        .and_then(|words| reflection::reflect(&words))
+        .map_err(Error::ShaderInterface)?;
+
+    let mut mismatches = reflection::check_vertex_input::<Vertex>(&interface, VERTEX_ATTRIBUTES);
+    mismatches.extend(reflection::check_uniform_block(
+        &interface,
+        "UniformBufferObject",
+        &rust_layout!(UniformBufferObject { model, view, proj }),
+        std::mem::size_of::<UniformBufferObject>(),
+    ));
+
+    let interface = reflection::compile_glsl(&format!("{}/object_uniforms.vert", shader_dir),
+        shaderc::ShaderKind::Vertex)
+        .and_then(|words| reflection::reflect(&words))
+        .map_err(Error::ShaderInterface)?;
+    mismatches.extend(reflection::check_uniform_block(
+        &interface,
+        "ObjectUniforms",
+        &rust_layout!(ObjectUniforms { model, tint }),
+        std::mem::size_of::<ObjectUniforms>(),
+    ));
+    Ok(mismatches)
+}
+
+fn run() -> Result<(), Error> {
+    let config = Config::from_args().map_err(Error::Config)?;
+    if config.print_config {
+        print!("{}", config.to_toml());
+        return Ok(());
+    }
+    debug!(target: logging::INSTANCE, "{:?}", config);
+
+    let mut app = HelloTriangleApplication::initialize(config)?;
+    let result = app.main_loop();
+    app.shutdown();
+    result
+}
+
 fn main() {
-    let mut app = HelloTriangleApplication::initialize();
-    app.main_loop();
+    logging::init();
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        process::exit(err.exit_code());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shader_interface_matches() {
+        assert_eq!(check_shader_interface(&Config::default().assets.shader_dir).unwrap(), vec![]);
+    }
 }
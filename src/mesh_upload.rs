@@ -0,0 +1,132 @@
+//! Uploads the vertices and indices of many meshes into a single device-local buffer.
+//!
+//! `create_vertex_buffer` and `create_index_buffer` each create their own `ImmutableBuffer`
+//! (with its own staging buffer and copy submission) and wait for it. As the conclusion of the
+//! tutorial's staging buffer chapter recommends, here all meshes are packed into one buffer,
+//! written through one staging buffer and copied with a single submission. Each mesh gets slices
+//! of that buffer, which can be passed to `draw_indexed` like separate buffers.
+use std::mem;
+use std::slice;
+use std::sync::Arc;
+
+use vulkano::buffer::{BufferAccess, BufferSlice, BufferUsage, CpuAccessibleBuffer, ImmutableBuffer, TypedBufferAccess};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::device::Queue;
+use vulkano::sync::GpuFuture;
+
+use error::Error;
+
+/// Offsets of the vertex and index data are aligned to this (index data needs at least 2 or 4).
+const ALIGNMENT: usize = 16;
+
+pub struct Mesh<V> {
+    pub vertices: Vec<V>,
+    pub indices: Vec<u16>,
+}
+
+/// A mesh in the packed buffer; the slices have the same types as the app's standalone buffers.
+pub struct UploadedMesh {
+    pub vertex_buffer: Arc<BufferAccess + Send + Sync>,
+    pub index_buffer: Arc<TypedBufferAccess<Content=[u16]> + Send + Sync>,
+}
+
+/// Counted while uploading, so it shows what the upload actually needed.
+#[derive(Debug, Default)]
+pub struct UploadReport {
+    pub meshes: usize,
+    pub bytes: usize,
+    pub device_local_allocations: usize,
+    pub staging_allocations: usize,
+    pub submissions: usize,
+}
+
+impl UploadReport {
+    /// What uploading each mesh's vertices and indices separately (like the tutorial) would need.
+    pub fn separate_allocations(&self) -> usize {
+        self.meshes * 2
+    }
+}
+
+fn align(offset: usize) -> usize {
+    (offset + ALIGNMENT - 1) / ALIGNMENT * ALIGNMENT
+}
+
+fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * mem::size_of::<T>()) }
+}
+
+pub fn upload_meshes<V>(graphics_queue: &Arc<Queue>, meshes: &[Mesh<V>])
+    -> Result<(Vec<UploadedMesh>, UploadReport), Error>
+    where V: Copy + Send + Sync + 'static
+{
+    // (vertex range, index range) in bytes for each mesh
+    let mut ranges = Vec::with_capacity(meshes.len());
+    let mut data: Vec<u8> = Vec::new();
+    for mesh in meshes {
+        let vertex_start = align(data.len());
+        data.resize(vertex_start, 0);
+        data.extend_from_slice(as_bytes(&mesh.vertices));
+        let vertex_end = data.len();
+
+        let index_start = align(data.len());
+        data.resize(index_start, 0);
+        data.extend_from_slice(as_bytes(&mesh.indices));
+        let index_end = data.len();
+
+        ranges.push((vertex_start .. vertex_end, index_start .. index_end));
+    }
+
+    let mut report = UploadReport { meshes: meshes.len(), bytes: data.len(), ..UploadReport::default() };
+    let device = graphics_queue.device();
+
+    let staging_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+        data.iter().cloned())
+        .map_err(Error::Memory)?;
+    report.staging_allocations += 1;
+
+    let usage = BufferUsage {
+        vertex_buffer: true,
+        index_buffer: true,
+        transfer_destination: true,
+        .. BufferUsage::none()
+    };
+    // NOTE: like `ImmutableBuffer::from_iter`, but with each step done (and counted) here
+    let (buffer, initialization) = unsafe {
+        ImmutableBuffer::<[u8]>::uninitialized_array(device.clone(), data.len(), usage,
+            Some(graphics_queue.family()))
+    }.map_err(Error::Memory)?;
+    report.device_local_allocations += 1;
+
+    let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), graphics_queue.family())
+        .map_err(Error::command_buffer)?
+        .copy_buffer(staging_buffer, initialization)
+        .map_err(Error::command_buffer)?
+        .build()
+        .map_err(Error::command_buffer)?;
+    command_buffer.execute(graphics_queue.clone())
+        .map_err(Error::Execute)?
+        .flush()
+        .map_err(Error::Upload)?;
+    report.submissions += 1;
+
+    let uploaded = ranges.into_iter()
+        .map(|(vertex_range, index_range)| {
+            let vertices = BufferSlice::from_typed_buffer_access(buffer.clone())
+                .slice(vertex_range)
+                .unwrap();
+            let indices = BufferSlice::from_typed_buffer_access(buffer.clone())
+                .slice(index_range)
+                .unwrap();
+            // NOTE: safe because we wrote exactly these types at these (aligned) offsets
+            let (vertices, indices) = unsafe {
+                (vertices.reinterpret::<[V]>(), indices.reinterpret::<[u16]>())
+            };
+            UploadedMesh {
+                vertex_buffer: Arc::new(vertices),
+                index_buffer: Arc::new(indices),
+            }
+        })
+        .collect();
+
+    Ok((uploaded, report))
+}
@@ -0,0 +1,32 @@
+//! Per-object uniform data for many objects in a single buffer.
+//!
+//! The usual way is a single descriptor set with a dynamic uniform buffer and a dynamic offset per
+//! draw. Vulkano 0.11 can't bind dynamic offsets (`AutoCommandBufferBuilder` always passes none),
+//! so instead the entries are an array in a storage buffer and each draw passes the index of its
+//! entry in a push constant. That's a single buffer and a single descriptor set per swap chain
+//! image, however many objects there are.
+//!
+//! Like the other per-frame data, the entries are written into the upload ring every frame and
+//! copied into the (device-local) buffer of the swap chain image.
+use std::sync::Arc;
+
+use vulkano::buffer::{BufferUsage, DeviceLocalBuffer};
+use vulkano::device::{Device, Queue};
+use vulkano::memory::DeviceMemoryAllocError;
+
+pub struct ObjectUniformBuffer<T> {
+    buffer: Arc<DeviceLocalBuffer<[T]>>,
+}
+
+impl<T: Send + Sync + 'static> ObjectUniformBuffer<T> {
+    /// One entry per object; the entries are undefined until the first copy.
+    pub fn new(device: &Arc<Device>, queue: &Arc<Queue>, count: usize) -> Result<Self, DeviceMemoryAllocError> {
+        let usage = BufferUsage { storage_buffer: true, transfer_destination: true, ..BufferUsage::none() };
+        let buffer = DeviceLocalBuffer::array(device.clone(), count, usage, Some(queue.family()))?;
+        Ok(Self { buffer })
+    }
+
+    pub fn buffer(&self) -> &Arc<DeviceLocalBuffer<[T]>> {
+        &self.buffer
+    }
+}
@@ -0,0 +1,44 @@
+//! Keeps built pipelines around, so switching between variants (e.g. different specialization
+//! constants or pipeline descriptions) doesn't rebuild them every time.
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use vulkano::pipeline::GraphicsPipelineAbstract;
+
+pub struct PipelineCache<K: Hash + Eq> {
+    pipelines: HashMap<K, Arc<GraphicsPipelineAbstract + Send + Sync>>,
+}
+
+impl<K: Hash + Eq + Clone> PipelineCache<K> {
+    pub fn new() -> Self {
+        Self { pipelines: HashMap::new() }
+    }
+
+    /// Only caches successfully created pipelines.
+    pub fn get_or_create<F, E>(&mut self, key: &K, create: F) -> Result<Arc<GraphicsPipelineAbstract + Send + Sync>, E>
+        where F: FnOnce(&K) -> Result<Arc<GraphicsPipelineAbstract + Send + Sync>, E>
+    {
+        if let Some(pipeline) = self.pipelines.get(key) {
+            return Ok(pipeline.clone());
+        }
+        let pipeline = create(key)?;
+        self.pipelines.insert(key.clone(), pipeline.clone());
+        Ok(pipeline)
+    }
+
+    pub fn pipelines(&self) -> impl Iterator<Item=&Arc<GraphicsPipelineAbstract + Send + Sync>> {
+        self.pipelines.values()
+    }
+
+    /// Needs to be called when something all pipelines depend on changes (render pass, viewport).
+    pub fn clear(&mut self) {
+        self.pipelines.clear();
+    }
+}
+
+impl<K: Hash + Eq + Clone> Default for PipelineCache<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
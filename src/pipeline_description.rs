@@ -0,0 +1,397 @@
+//! Data-driven description of the fixed-function pipeline state.
+//!
+//! `create_graphics_pipeline` used to hardcode the rasterizer/blend state in the builder chain.
+//! A `PipelineDescription` can be deserialized (e.g. from TOML), so variants like wireframe,
+//! alpha-blended or double-sided pipelines don't need code changes. `pipeline` in the config
+//! selects one of the presets or a file (see `PipelineDescription::load`).
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+
+use toml;
+use vulkano::pipeline::GraphicsPipelineBuilder;
+use vulkano::pipeline::input_assembly::PrimitiveTopology;
+use vulkano::pipeline::depth_stencil::{DepthStencil, Compare};
+use vulkano::pipeline::blend::{AttachmentBlend, BlendOp, BlendFactor};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topology {
+    PointList,
+    LineList,
+    LineStrip,
+    TriangleList,
+    TriangleStrip,
+    TriangleFan,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrontFace {
+    Clockwise,
+    CounterClockwise,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareOp {
+    Never,
+    Less,
+    Equal,
+    LessOrEqual,
+    Greater,
+    NotEqual,
+    GreaterOrEqual,
+    Always,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendFactorDescription {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendOpDescription {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DepthState {
+    pub test: bool,
+    pub write: bool,
+    pub compare: CompareOp,
+}
+
+impl Default for DepthState {
+    fn default() -> Self {
+        Self { test: false, write: false, compare: CompareOp::Less }
+    }
+}
+
+/// Blend state of one color attachment.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachmentBlendDescription {
+    pub enabled: bool,
+    pub color_op: BlendOpDescription,
+    pub color_source: BlendFactorDescription,
+    pub color_destination: BlendFactorDescription,
+    pub alpha_op: BlendOpDescription,
+    pub alpha_source: BlendFactorDescription,
+    pub alpha_destination: BlendFactorDescription,
+}
+
+impl AttachmentBlendDescription {
+    pub fn pass_through() -> Self {
+        Self {
+            enabled: false,
+            color_op: BlendOpDescription::Add,
+            color_source: BlendFactorDescription::Zero,
+            color_destination: BlendFactorDescription::One,
+            alpha_op: BlendOpDescription::Add,
+            alpha_source: BlendFactorDescription::Zero,
+            alpha_destination: BlendFactorDescription::One,
+        }
+    }
+
+    pub fn alpha_blending() -> Self {
+        Self {
+            enabled: true,
+            color_op: BlendOpDescription::Add,
+            color_source: BlendFactorDescription::SrcAlpha,
+            color_destination: BlendFactorDescription::OneMinusSrcAlpha,
+            alpha_op: BlendOpDescription::Add,
+            alpha_source: BlendFactorDescription::SrcAlpha,
+            alpha_destination: BlendFactorDescription::OneMinusSrcAlpha,
+        }
+    }
+}
+
+impl Default for AttachmentBlendDescription {
+    fn default() -> Self {
+        Self::pass_through()
+    }
+}
+
+/// NOTE: no depth bias, Vulkano's `GraphicsPipelineBuilder` has no way to set it (yet)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PipelineDescription {
+    pub topology: Topology,
+    pub polygon_mode: PolygonMode,
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    pub line_width: f32,
+    pub depth: DepthState,
+    /// One entry per color attachment of the subpass. A single entry is used for all attachments.
+    pub blend: Vec<AttachmentBlendDescription>,
+}
+
+/// Same state as the hardcoded builder chain of the tutorial.
+impl Default for PipelineDescription {
+    fn default() -> Self {
+        Self {
+            topology: Topology::TriangleList,
+            polygon_mode: PolygonMode::Fill,
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::Clockwise,
+            line_width: 1.0,
+            depth: DepthState::default(),
+            blend: vec![AttachmentBlendDescription::pass_through()],
+        }
+    }
+}
+
+// NOTE: manual impls because of the line width; NaN isn't valid anyway
+impl Eq for PipelineDescription {}
+
+impl Hash for PipelineDescription {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.topology.hash(state);
+        self.polygon_mode.hash(state);
+        self.cull_mode.hash(state);
+        self.front_face.hash(state);
+        self.line_width.to_bits().hash(state);
+        self.depth.hash(state);
+        self.blend.hash(state);
+    }
+}
+
+#[derive(Debug)]
+pub enum PipelineDescriptionError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    /// Vulkano's `GraphicsPipelineBuilder` has no way to set the depth bias (yet)
+    DepthBiasUnsupported,
+}
+
+impl fmt::Display for PipelineDescriptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineDescriptionError::Io(err) => write!(f, "failed to read pipeline description: {}", err),
+            PipelineDescriptionError::Parse(err) => write!(f, "failed to parse pipeline description: {}", err),
+            PipelineDescriptionError::DepthBiasUnsupported =>
+                write!(f, "depth bias can't be set with Vulkano's pipeline builder"),
+        }
+    }
+}
+
+/// Names of the presets that can be used instead of a file.
+pub const PRESETS: &[&str] = &["default", "alpha_blended", "double_sided"];
+
+impl PipelineDescription {
+    pub fn from_toml(source: &str) -> Result<Self, PipelineDescriptionError> {
+        // NOTE: checked explicitly for a better message than "unknown field"
+        if let Ok(toml::Value::Table(table)) = source.parse::<toml::Value>() {
+            if table.contains_key("depth_bias") {
+                return Err(PipelineDescriptionError::DepthBiasUnsupported);
+            }
+        }
+        toml::from_str(source).map_err(PipelineDescriptionError::Parse)
+    }
+
+    /// One of the `PRESETS`, or otherwise the path of a file for `from_toml`.
+    pub fn load(preset_or_path: &str) -> Result<Self, PipelineDescriptionError> {
+        match preset_or_path {
+            "default" => Ok(Self::default()),
+            "alpha_blended" => Ok(Self::alpha_blended()),
+            "double_sided" => Ok(Self::double_sided()),
+            path => {
+                let source = fs::read_to_string(path).map_err(PipelineDescriptionError::Io)?;
+                Self::from_toml(&source)
+            }
+        }
+    }
+
+    /// The same pipeline with lines instead of filled polygons, and without culling.
+    /// Requires the `fill_mode_non_solid` device feature.
+    pub fn wireframe(&self) -> Self {
+        Self { polygon_mode: PolygonMode::Line, cull_mode: CullMode::None, ..self.clone() }
+    }
+
+    pub fn alpha_blended() -> Self {
+        Self { blend: vec![AttachmentBlendDescription::alpha_blending()], ..Self::default() }
+    }
+
+    pub fn double_sided() -> Self {
+        Self { cull_mode: CullMode::None, ..Self::default() }
+    }
+
+    /// Applies the description to a pipeline builder; shaders, vertex input, viewports and
+    /// render pass are left to the caller.
+    pub fn apply<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>(
+        &self,
+        builder: GraphicsPipelineBuilder<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>,
+    ) -> GraphicsPipelineBuilder<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp> {
+        let builder = builder.primitive_topology(match self.topology {
+            Topology::PointList => PrimitiveTopology::PointList,
+            Topology::LineList => PrimitiveTopology::LineList,
+            Topology::LineStrip => PrimitiveTopology::LineStrip,
+            Topology::TriangleList => PrimitiveTopology::TriangleList,
+            Topology::TriangleStrip => PrimitiveTopology::TriangleStrip,
+            Topology::TriangleFan => PrimitiveTopology::TriangleFan,
+        });
+
+        let builder = match self.polygon_mode {
+            PolygonMode::Fill => builder.polygon_mode_fill(),
+            PolygonMode::Line => builder.polygon_mode_line(),
+            PolygonMode::Point => builder.polygon_mode_point(),
+        };
+
+        let builder = match self.cull_mode {
+            CullMode::None => builder.cull_mode_disabled(),
+            CullMode::Front => builder.cull_mode_front(),
+            CullMode::Back => builder.cull_mode_back(),
+            CullMode::FrontAndBack => builder.cull_mode_front_and_back(),
+        };
+
+        let builder = match self.front_face {
+            FrontFace::Clockwise => builder.front_face_clockwise(),
+            FrontFace::CounterClockwise => builder.front_face_counter_clockwise(),
+        };
+
+        let builder = builder
+            .line_width(self.line_width)
+            .depth_stencil(self.depth.to_vulkano());
+
+        match self.blend.len() {
+            0 => builder.blend_pass_through(),
+            1 => builder.blend_collective(self.blend[0].to_vulkano()),
+            _ => builder.blend_individual(self.blend.iter().map(|b| b.to_vulkano())),
+        }
+    }
+}
+
+impl DepthState {
+    fn to_vulkano(&self) -> DepthStencil {
+        if !self.test {
+            return DepthStencil::disabled();
+        }
+        DepthStencil {
+            depth_write: self.write,
+            depth_compare: match self.compare {
+                CompareOp::Never => Compare::Never,
+                CompareOp::Less => Compare::Less,
+                CompareOp::Equal => Compare::Equal,
+                CompareOp::LessOrEqual => Compare::LessOrEqual,
+                CompareOp::Greater => Compare::Greater,
+                CompareOp::NotEqual => Compare::NotEqual,
+                CompareOp::GreaterOrEqual => Compare::GreaterOrEqual,
+                CompareOp::Always => Compare::Always,
+            },
+            ..DepthStencil::disabled()
+        }
+    }
+}
+
+impl AttachmentBlendDescription {
+    fn to_vulkano(&self) -> AttachmentBlend {
+        fn factor(factor: BlendFactorDescription) -> BlendFactor {
+            match factor {
+                BlendFactorDescription::Zero => BlendFactor::Zero,
+                BlendFactorDescription::One => BlendFactor::One,
+                BlendFactorDescription::SrcColor => BlendFactor::SrcColor,
+                BlendFactorDescription::OneMinusSrcColor => BlendFactor::OneMinusSrcColor,
+                BlendFactorDescription::DstColor => BlendFactor::DstColor,
+                BlendFactorDescription::OneMinusDstColor => BlendFactor::OneMinusDstColor,
+                BlendFactorDescription::SrcAlpha => BlendFactor::SrcAlpha,
+                BlendFactorDescription::OneMinusSrcAlpha => BlendFactor::OneMinusSrcAlpha,
+                BlendFactorDescription::DstAlpha => BlendFactor::DstAlpha,
+                BlendFactorDescription::OneMinusDstAlpha => BlendFactor::OneMinusDstAlpha,
+            }
+        }
+        fn op(op: BlendOpDescription) -> BlendOp {
+            match op {
+                BlendOpDescription::Add => BlendOp::Add,
+                BlendOpDescription::Subtract => BlendOp::Subtract,
+                BlendOpDescription::ReverseSubtract => BlendOp::ReverseSubtract,
+                BlendOpDescription::Min => BlendOp::Min,
+                BlendOpDescription::Max => BlendOp::Max,
+            }
+        }
+
+        AttachmentBlend {
+            enabled: self.enabled,
+            color_op: op(self.color_op),
+            color_source: factor(self.color_source),
+            color_destination: factor(self.color_destination),
+            alpha_op: op(self.alpha_op),
+            alpha_source: factor(self.alpha_source),
+            alpha_destination: factor(self.alpha_destination),
+            ..AttachmentBlend::pass_through()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_toml() {
+        let description = PipelineDescription::from_toml("
+            cull_mode = \"none\"
+            [depth]
+            test = true
+        ").unwrap();
+        assert_eq!(description.cull_mode, CullMode::None);
+        assert!(description.depth.test);
+        assert_eq!(description.topology, PipelineDescription::default().topology);
+    }
+
+    #[test]
+    fn unsupported_fields() {
+        match PipelineDescription::from_toml("[depth_bias]\nconstant_factor = 1.0") {
+            Err(PipelineDescriptionError::DepthBiasUnsupported) => (),
+            result => panic!("expected DepthBiasUnsupported, got {:?}", result),
+        }
+        for source in &["cul_mode = \"none\"", "[depth]\ntets = true", "[[blend]]\ncolor_opp = \"add\""] {
+            match PipelineDescription::from_toml(source) {
+                Err(PipelineDescriptionError::Parse(_)) => (),
+                result => panic!("expected a parse error for {:?}, got {:?}", source, result),
+            }
+        }
+    }
+
+    #[test]
+    fn presets() {
+        for preset in PRESETS {
+            assert!(PipelineDescription::load(preset).is_ok(), "{}", preset);
+        }
+        assert_eq!(PipelineDescription::load("double_sided").unwrap(), PipelineDescription::double_sided());
+    }
+}
@@ -0,0 +1,198 @@
+//! Projection and view matrices for Vulkan's conventions.
+//!
+//! `cgmath::perspective` and `cgmath::ortho` produce OpenGL clip space: Y up and depth from -1 to 1.
+//! Vulkan's clip space has Y pointing down and depth from 0 to 1, so the tutorial flips `proj.y.y`
+//! and half of the depth range ends up behind the near plane. The matrices here map directly to
+//! Vulkan's clip space from a right-handed view space (looking down -Z, Y up, like `look_at`).
+//!
+//! The reverse-Z variants map the near plane to depth 1 and the far plane to 0, which distributes
+//! floating point depth precision much better; they need a `Greater` depth test and clearing depth to 0.
+use cgmath::{Matrix4, Point3, Rad, Vector3};
+
+/// Near plane at depth 0, far plane at depth 1.
+pub fn perspective(fovy: Rad<f32>, aspect: f32, near: f32, far: f32) -> Matrix4<f32> {
+    let f = 1.0 / (fovy.0 / 2.0).tan();
+    Matrix4::new(
+        f / aspect, 0.0, 0.0, 0.0,
+        0.0, -f, 0.0, 0.0,
+        0.0, 0.0, far / (near - far), -1.0,
+        0.0, 0.0, near * far / (near - far), 0.0,
+    )
+}
+
+/// Near plane at depth 1, far plane at depth 0.
+pub fn perspective_reverse_z(fovy: Rad<f32>, aspect: f32, near: f32, far: f32) -> Matrix4<f32> {
+    let f = 1.0 / (fovy.0 / 2.0).tan();
+    Matrix4::new(
+        f / aspect, 0.0, 0.0, 0.0,
+        0.0, -f, 0.0, 0.0,
+        0.0, 0.0, near / (far - near), -1.0,
+        0.0, 0.0, near * far / (far - near), 0.0,
+    )
+}
+
+/// `perspective` with the far plane at infinity (depth approaches 1).
+pub fn perspective_infinite(fovy: Rad<f32>, aspect: f32, near: f32) -> Matrix4<f32> {
+    let f = 1.0 / (fovy.0 / 2.0).tan();
+    Matrix4::new(
+        f / aspect, 0.0, 0.0, 0.0,
+        0.0, -f, 0.0, 0.0,
+        0.0, 0.0, -1.0, -1.0,
+        0.0, 0.0, -near, 0.0,
+    )
+}
+
+/// `perspective_reverse_z` with the far plane at infinity (depth approaches 0).
+pub fn perspective_infinite_reverse_z(fovy: Rad<f32>, aspect: f32, near: f32) -> Matrix4<f32> {
+    let f = 1.0 / (fovy.0 / 2.0).tan();
+    Matrix4::new(
+        f / aspect, 0.0, 0.0, 0.0,
+        0.0, -f, 0.0, 0.0,
+        0.0, 0.0, 0.0, -1.0,
+        0.0, 0.0, near, 0.0,
+    )
+}
+
+/// Maps the box to x and y from -1 to 1 (`top` at the top of the screen) and depth from 0 (near) to 1 (far).
+pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Matrix4<f32> {
+    Matrix4::new(
+        2.0 / (right - left), 0.0, 0.0, 0.0,
+        0.0, -2.0 / (top - bottom), 0.0, 0.0,
+        0.0, 0.0, 1.0 / (near - far), 0.0,
+        -(right + left) / (right - left), (top + bottom) / (top - bottom), near / (near - far), 1.0,
+    )
+}
+
+/// View matrix of a camera at `eye` looking at `target`.
+pub fn look_at(eye: Point3<f32>, target: Point3<f32>, up: Vector3<f32>) -> Matrix4<f32> {
+    Matrix4::look_at(eye, target, up)
+}
+
+/// View matrix of a camera at `eye` looking in `direction`.
+pub fn look_to(eye: Point3<f32>, direction: Vector3<f32>, up: Vector3<f32>) -> Matrix4<f32> {
+    Matrix4::look_at_dir(eye, direction, up)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, InnerSpace, Vector4};
+
+    const EPSILON: f32 = 1e-5;
+
+    /// Normalized device coordinates of a view space point.
+    fn project(matrix: Matrix4<f32>, x: f32, y: f32, z: f32) -> Vector3<f32> {
+        let clip = matrix * Vector4::new(x, y, z, 1.0);
+        clip.truncate() / clip.w
+    }
+
+    fn assert_close(actual: Vector3<f32>, expected: Vector3<f32>) {
+        assert!((actual - expected).magnitude() < EPSILON, "{:?} != {:?}", actual, expected);
+    }
+
+    fn fovy() -> Rad<f32> {
+        Rad::from(Deg(60.0))
+    }
+
+    #[test]
+    fn perspective_maps_near_to_0_and_far_to_1() {
+        let proj = perspective(fovy(), 1.5, 0.1, 100.0);
+        assert!(project(proj, 0.0, 0.0, -0.1).z.abs() < EPSILON);
+        assert!((project(proj, 0.0, 0.0, -100.0).z - 1.0).abs() < EPSILON);
+        // in between, depth increases with distance
+        let middle = project(proj, 0.0, 0.0, -1.0).z;
+        assert!(middle > 0.0 && middle < 1.0);
+    }
+
+    #[test]
+    fn perspective_reverse_z_maps_near_to_1_and_far_to_0() {
+        let proj = perspective_reverse_z(fovy(), 1.5, 0.1, 100.0);
+        assert!((project(proj, 0.0, 0.0, -0.1).z - 1.0).abs() < EPSILON);
+        assert!(project(proj, 0.0, 0.0, -100.0).z.abs() < EPSILON);
+        let middle = project(proj, 0.0, 0.0, -1.0).z;
+        assert!(middle > 0.0 && middle < 1.0);
+    }
+
+    #[test]
+    fn perspective_y_points_down() {
+        let projections = [
+            perspective(fovy(), 1.5, 0.1, 100.0),
+            perspective_reverse_z(fovy(), 1.5, 0.1, 100.0),
+            perspective_infinite(fovy(), 1.5, 0.1),
+            perspective_infinite_reverse_z(fovy(), 1.5, 0.1),
+        ];
+        for &proj in projections.iter() {
+            // up in view space is up on the screen, i.e. negative Y in Vulkan's clip space
+            assert!(project(proj, 0.0, 1.0, -5.0).y < 0.0);
+            assert!(project(proj, 1.0, 0.0, -5.0).x > 0.0);
+        }
+    }
+
+    #[test]
+    fn perspective_edges_match_the_field_of_view() {
+        let aspect = 1.5;
+        let proj = perspective(fovy(), aspect, 0.1, 100.0);
+        let half_height = (fovy().0 / 2.0).tan() * 10.0;
+        let top_right = project(proj, half_height * aspect, half_height, -10.0);
+        assert!((top_right.x - 1.0).abs() < EPSILON);
+        assert!((top_right.y + 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn perspective_infinite_far_limit() {
+        let proj = perspective_infinite(fovy(), 1.5, 0.1);
+        assert!(project(proj, 0.0, 0.0, -0.1).z.abs() < EPSILON);
+        let far = project(proj, 0.0, 0.0, -1.0e6).z;
+        assert!(far < 1.0 && far > 1.0 - EPSILON);
+        // the same as the finite projection for far enough far planes
+        let finite = perspective(fovy(), 1.5, 0.1, 1.0e6);
+        assert_close(project(proj, 1.0, 2.0, -10.0), project(finite, 1.0, 2.0, -10.0));
+    }
+
+    #[test]
+    fn perspective_infinite_reverse_z_far_limit() {
+        let proj = perspective_infinite_reverse_z(fovy(), 1.5, 0.1);
+        assert!((project(proj, 0.0, 0.0, -0.1).z - 1.0).abs() < EPSILON);
+        let far = project(proj, 0.0, 0.0, -1.0e6).z;
+        assert!(far > 0.0 && far < EPSILON);
+        let finite = perspective_reverse_z(fovy(), 1.5, 0.1, 1.0e6);
+        assert_close(project(proj, 1.0, 2.0, -10.0), project(finite, 1.0, 2.0, -10.0));
+    }
+
+    #[test]
+    fn orthographic_corners() {
+        let proj = orthographic(-2.0, 4.0, -1.0, 3.0, 0.5, 10.0);
+        // left, bottom, near: bottom is at the bottom of the screen (Y = 1 in Vulkan)
+        assert_close(project(proj, -2.0, -1.0, -0.5), Vector3::new(-1.0, 1.0, 0.0));
+        // right, top, far
+        assert_close(project(proj, 4.0, 3.0, -10.0), Vector3::new(1.0, -1.0, 1.0));
+        assert_close(project(proj, -2.0, 3.0, -10.0), Vector3::new(-1.0, -1.0, 1.0));
+        assert_close(project(proj, 4.0, -1.0, -0.5), Vector3::new(1.0, 1.0, 0.0));
+        // center
+        assert_close(project(proj, 1.0, 1.0, -5.25), Vector3::new(0.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn look_at_basis() {
+        // from +X towards the origin, Z up (like the app's camera)
+        let view = look_at(Point3::new(2.0, 0.0, 0.0), Point3::new(0.0, 0.0, 0.0), Vector3::unit_z());
+        let transform = |p: Point3<f32>| project(view, p.x, p.y, p.z);
+        // the target is straight ahead (-Z in view space)
+        assert_close(transform(Point3::new(0.0, 0.0, 0.0)), Vector3::new(0.0, 0.0, -2.0));
+        // world +Y is to the right, world +Z is up
+        assert_close(transform(Point3::new(2.0, 1.0, 0.0)), Vector3::new(1.0, 0.0, 0.0));
+        assert_close(transform(Point3::new(2.0, 0.0, 1.0)), Vector3::new(0.0, 1.0, 0.0));
+        // the eye is at the origin of view space
+        assert_close(transform(Point3::new(2.0, 0.0, 0.0)), Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn look_to_matches_look_at() {
+        let eye = Point3::new(1.0, 2.0, 3.0);
+        let target = Point3::new(-1.0, 0.5, 0.0);
+        let from_target = look_at(eye, target, Vector3::unit_z());
+        let from_direction = look_to(eye, target - eye, Vector3::unit_z());
+        let p = Point3::new(0.3, -0.7, 1.1);
+        assert_close(project(from_target, p.x, p.y, p.z), project(from_direction, p.x, p.y, p.z));
+    }
+}
@@ -0,0 +1,155 @@
+//! Bookkeeping for recovering from a lost device or surface (e.g. after a driver reset).
+//!
+//! `draw_frame` reports the loss, the next frame rebuilds what was lost. After `MAX_ATTEMPTS`
+//! failed attempts without a frame presented in between, the app gives up.
+//! Either way, the window is replaced by a new one (see `replace_window` in main.rs).
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use logging;
+
+/// Consecutive attempts before giving up
+pub const MAX_ATTEMPTS: u32 = 3;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Lost {
+    /// everything created from the device needs to be rebuilt
+    Device,
+    /// only the surface and swap chain need to be rebuilt
+    Surface,
+}
+
+impl fmt::Display for Lost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Lost::Device => write!(f, "device"),
+            Lost::Surface => write!(f, "surface"),
+        }
+    }
+}
+
+pub struct RecoveryEntry {
+    /// since the start of the app
+    pub time: Duration,
+    pub lost: Lost,
+    pub attempt: u32,
+    /// how long rebuilding took, or why it failed
+    pub result: Result<Duration, String>,
+}
+
+impl fmt::Display for RecoveryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self.time.as_secs() as f32 + self.time.subsec_nanos() as f32 / 1_000_000_000.0;
+        write!(f, "[{:.3}s] lost {}, attempt {}/{}: ", seconds, self.lost, self.attempt, MAX_ATTEMPTS)?;
+        match self.result {
+            Ok(duration) => write!(f, "recovered in {:.1}ms",
+                duration.as_secs() as f32 * 1000.0 + duration.subsec_nanos() as f32 / 1_000_000.0),
+            Err(ref err) => write!(f, "failed: {}", err),
+        }
+    }
+}
+
+pub struct Recovery {
+    start: Instant,
+    pending: Option<Lost>,
+    attempts: u32,
+    log: Vec<RecoveryEntry>,
+}
+
+impl Default for Recovery {
+    fn default() -> Self {
+        Self { start: Instant::now(), pending: None, attempts: 0, log: vec![] }
+    }
+}
+
+impl Recovery {
+    /// A lost device includes the surface, so it isn't downgraded by a later lost surface.
+    pub fn lost(&mut self, lost: Lost) {
+        if self.pending != Some(Lost::Device) {
+            self.pending = Some(lost);
+        }
+    }
+
+    pub fn pending(&self) -> Option<Lost> {
+        self.pending
+    }
+
+    pub fn can_retry(&self) -> bool {
+        self.attempts < MAX_ATTEMPTS
+    }
+
+    /// Logs an attempt (and prints it); the loss stays pending if it failed.
+    pub fn record<E: fmt::Display>(&mut self, lost: Lost, result: Result<(), E>, duration: Duration) {
+        self.attempts += 1;
+        if result.is_ok() {
+            self.pending = None;
+        }
+        let entry = RecoveryEntry {
+            time: self.start.elapsed(),
+            lost,
+            attempt: self.attempts,
+            result: result.map(|()| duration).map_err(|err| err.to_string()),
+        };
+        warn!(target: logging::DEVICE, "{}", entry);
+        self.log.push(entry);
+    }
+
+    /// Resets the attempt count.
+    pub fn frame_presented(&mut self) {
+        self.attempts = 0;
+    }
+
+    pub fn log(&self) -> &[RecoveryEntry] {
+        &self.log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fail(recovery: &mut Recovery, lost: Lost) {
+        recovery.record(lost, Err("failed"), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let mut recovery = Recovery::default();
+        recovery.lost(Lost::Surface);
+        for _ in 0..MAX_ATTEMPTS {
+            assert!(recovery.can_retry());
+            fail(&mut recovery, Lost::Surface);
+        }
+        assert!(!recovery.can_retry());
+        assert_eq!(recovery.pending(), Some(Lost::Surface));
+        assert_eq!(recovery.log().len(), MAX_ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn presented_frame_resets_attempts() {
+        let mut recovery = Recovery::default();
+        recovery.lost(Lost::Device);
+        for _ in 0..MAX_ATTEMPTS {
+            fail(&mut recovery, Lost::Device);
+        }
+        recovery.frame_presented();
+        assert!(recovery.can_retry());
+
+        recovery.record::<String>(Lost::Device, Ok(()), Duration::from_millis(1));
+        assert_eq!(recovery.pending(), None);
+        assert_eq!(recovery.log().last().unwrap().attempt, 1);
+    }
+
+    #[test]
+    fn lost_device_not_downgraded() {
+        let mut recovery = Recovery::default();
+        recovery.lost(Lost::Device);
+        recovery.lost(Lost::Surface);
+        assert_eq!(recovery.pending(), Some(Lost::Device));
+
+        let mut recovery = Recovery::default();
+        recovery.lost(Lost::Surface);
+        recovery.lost(Lost::Device);
+        assert_eq!(recovery.pending(), Some(Lost::Device));
+    }
+}
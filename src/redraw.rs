@@ -0,0 +1,78 @@
+//! When `main_loop` renders a frame.
+//!
+//! `Continuous` renders as fast as presenting allows (optionally capped with `FrameLimiter`),
+//! `OnDemand` blocks on window events and only renders when something changed.
+use std::thread;
+use std::time::{Duration, Instant};
+
+use winit::WindowEvent;
+
+use config::FrameConfig;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RedrawMode {
+    /// A new frame in every iteration of the main loop
+    Continuous,
+    /// Only after resizes, refreshes, input or while something is animating
+    OnDemand,
+}
+
+impl RedrawMode {
+    /// `frame.on_demand` in the config (`--on-demand`) selects `OnDemand`
+    pub fn from_config(config: &FrameConfig) -> Self {
+        if config.on_demand {
+            RedrawMode::OnDemand
+        } else {
+            RedrawMode::Continuous
+        }
+    }
+}
+
+/// Whether the event can change what's on screen.
+pub fn needs_redraw(event: &WindowEvent) -> bool {
+    match *event {
+        WindowEvent::Resized(_)
+            | WindowEvent::HiDpiFactorChanged(_)
+            | WindowEvent::Refresh
+            | WindowEvent::KeyboardInput { .. }
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::CursorMoved { .. } => true,
+        _ => false,
+    }
+}
+
+/// Caps the frame rate by sleeping until the next frame is due.
+pub struct FrameLimiter {
+    interval: Duration,
+    next_frame: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new(max_fps: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / max_fps,
+            next_frame: Instant::now(),
+        }
+    }
+
+    /// `frame.max_fps` in the config (`--max-fps N`) caps the frame rate in continuous mode
+    pub fn from_config(config: &FrameConfig) -> Option<Self> {
+        if config.max_fps == 0 {
+            return None;
+        }
+        Some(Self::new(config.max_fps))
+    }
+
+    /// Call once per frame.
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        if now < self.next_frame {
+            thread::sleep(self.next_frame - now);
+            self.next_frame += self.interval;
+        } else {
+            // NOTE: don't try to catch up after a slow frame
+            self.next_frame = now + self.interval;
+        }
+    }
+}
@@ -0,0 +1,646 @@
+//! Minimal SPIR-V reflection to check the shader interface against the Rust side.
+//!
+//! Vulkano matches vertex attributes by name when building the pipeline, but a typo or a wrong
+//! type only shows up as a runtime error (or garbage on screen), and uniform blocks aren't
+//! checked at all. This module reads the vertex inputs and uniform block layouts from a
+//! SPIR-V module and compares them against a `Vertex` type (from `impl_vertex!`) and a
+//! `#[repr(C)]` uniform struct. None of this needs a GPU.
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use shaderc;
+use vulkano::pipeline::vertex::{Vertex, VertexMemberTy};
+
+const MAGIC_NUMBER: u32 = 0x0723_0203;
+
+// opcodes
+const OP_NAME: u32 = 5;
+const OP_MEMBER_NAME: u32 = 6;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+// decorations
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+// storage classes
+const STORAGE_CLASS_INPUT: u32 = 1;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+/// storage buffers since SPIR-V 1.3 (before that, they are `Uniform` too)
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+#[derive(Debug)]
+pub enum ReflectionError {
+    Io(String),
+    Compile(String),
+    InvalidSpirv(&'static str),
+}
+
+impl fmt::Display for ReflectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReflectionError::Io(msg) => write!(f, "failed to read shader: {}", msg),
+            ReflectionError::Compile(msg) => write!(f, "failed to compile shader: {}", msg),
+            ReflectionError::InvalidSpirv(msg) => write!(f, "invalid SPIR-V: {}", msg),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NumericKind {
+    Float,
+    Int,
+    Uint,
+}
+
+/// Format of a vertex attribute, e.g. `vec2` = 2 x 32 bit float.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AttributeFormat {
+    pub kind: NumericKind,
+    pub bits: u32,
+    pub components: u32,
+}
+
+impl fmt::Display for AttributeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            NumericKind::Float => "f",
+            NumericKind::Int => "i",
+            NumericKind::Uint => "u",
+        };
+        write!(f, "{}{}x{}", kind, self.bits, self.components)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VertexInput {
+    pub name: String,
+    pub location: u32,
+    /// `None` for types that can't be used as a single attribute (matrices, structs...)
+    pub format: Option<AttributeFormat>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockMember {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+/// A uniform or storage block, or a struct that is the element type of an array in one
+/// (like `ObjectUniforms` in `buffer ObjectBuffer { ObjectUniforms objects[]; };`).
+#[derive(Clone, Debug)]
+pub struct UniformBlock {
+    /// Name of the block type (`UniformBufferObject` in `uniform UniformBufferObject { .. } ubo;`)
+    /// or of the struct
+    pub name: String,
+    #[allow(dead_code)]
+    pub set: u32,
+    #[allow(dead_code)]
+    pub binding: u32,
+    /// for array elements, the array stride
+    pub size: u32,
+    pub members: Vec<BlockMember>,
+    pub array_element: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ShaderInterface {
+    pub vertex_inputs: Vec<VertexInput>,
+    pub uniform_blocks: Vec<UniformBlock>,
+}
+
+/// A member of a Rust struct that is mirrored by a uniform block. Use `rust_layout!` to build these.
+#[derive(Clone, Debug)]
+pub struct RustMember {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+}
+
+/// Returns the offset of `field` inside `value`; helper for `rust_layout!`.
+pub fn field_offset<T, F>(value: &T, field: &F) -> usize {
+    field as *const F as usize - value as *const T as usize
+}
+
+/// Builds a `Vec<RustMember>` describing the memory layout of the listed fields of a struct.
+/// The struct should be `#[repr(C)]`, otherwise the compiler is free to reorder fields.
+/// NOTE: measured on a zeroed value (`mem::offset_of!` needs a much newer compiler than this crate
+/// targets), so this is only for plain data like the uniform structs, for which all zeros is valid.
+macro_rules! rust_layout {
+    ($ty:ty { $($field:ident),* $(,)* }) => {{
+        let value: $ty = unsafe { ::std::mem::zeroed() };
+        vec![$(
+            $crate::reflection::RustMember {
+                name: stringify!($field),
+                offset: $crate::reflection::field_offset(&value, &value.$field),
+                size: ::std::mem::size_of_val(&value.$field),
+            }
+        ),*]
+    }};
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mismatch {
+    MissingVertexAttribute { name: String },
+    UnusedVertexAttribute { name: String },
+    VertexLocation { name: String, shader: u32, rust: u32 },
+    VertexFormat { name: String, shader: String, rust: String },
+    MissingUniformBlock { name: String },
+    MissingBlockMember { block: String, name: String },
+    ExtraBlockMember { block: String, name: String },
+    MemberOffset { block: String, name: String, shader: u32, rust: usize },
+    MemberSize { block: String, name: String, shader: u32, rust: usize },
+    BlockSize { block: String, shader: u32, rust: usize },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mismatch::MissingVertexAttribute { name } =>
+                write!(f, "vertex input `{}` has no matching field in the Rust vertex", name),
+            Mismatch::UnusedVertexAttribute { name } =>
+                write!(f, "Rust vertex field `{}` is not a vertex input of the shader", name),
+            Mismatch::VertexLocation { name, shader, rust } =>
+                write!(f, "vertex input `{}`: location {} in shader, {} in Rust", name, shader, rust),
+            Mismatch::VertexFormat { name, shader, rust } =>
+                write!(f, "vertex input `{}`: format {} in shader, {} in Rust", name, shader, rust),
+            Mismatch::MissingUniformBlock { name } =>
+                write!(f, "uniform block `{}` not found in shader", name),
+            Mismatch::MissingBlockMember { block, name } =>
+                write!(f, "{}.{} is missing in the Rust struct", block, name),
+            Mismatch::ExtraBlockMember { block, name } =>
+                write!(f, "{}.{} is not a member of the uniform block", block, name),
+            Mismatch::MemberOffset { block, name, shader, rust } =>
+                write!(f, "{}.{}: offset {} in shader, {} in Rust", block, name, shader, rust),
+            Mismatch::MemberSize { block, name, shader, rust } =>
+                write!(f, "{}.{}: size {} in shader, {} in Rust", block, name, shader, rust),
+            Mismatch::BlockSize { block, shader, rust } =>
+                write!(f, "{}: size {} in shader, {} in Rust", block, shader, rust),
+        }
+    }
+}
+
+/// Compiles a GLSL file to SPIR-V with shaderc (the same compiler `vulkano_shaders` uses).
+pub fn compile_glsl(path: &str, kind: shaderc::ShaderKind) -> Result<Vec<u32>, ReflectionError> {
+    let source = fs::read_to_string(Path::new(path))
+        .map_err(|e| ReflectionError::Io(format!("{}: {}", path, e)))?;
+    compile_glsl_source(&source, kind, path)
+}
+
+/// `compile_glsl` for source code that isn't in a file; `name` is only used in error messages.
+pub fn compile_glsl_source(source: &str, kind: shaderc::ShaderKind, name: &str) -> Result<Vec<u32>, ReflectionError> {
+    let mut compiler = shaderc::Compiler::new()
+        .ok_or_else(|| ReflectionError::Compile("failed to initialize shaderc".into()))?;
+    let artifact = compiler.compile_into_spirv(source, kind, name, "main", None)
+        .map_err(|e| ReflectionError::Compile(e.to_string()))?;
+    Ok(artifact.as_binary().to_vec())
+}
+
+enum Type {
+    Scalar { kind: NumericKind, bits: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct { members: Vec<u32> },
+    Pointer { pointee: u32 },
+    Other,
+}
+
+#[derive(Default)]
+struct Module {
+    names: HashMap<u32, String>,
+    member_names: HashMap<(u32, u32), String>,
+    decorations: HashMap<(u32, u32), u32>,
+    member_decorations: HashMap<(u32, u32, u32), u32>,
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u32>,
+    // (id, pointer type, storage class)
+    variables: Vec<(u32, u32, u32)>,
+    is_vertex_shader: bool,
+}
+
+fn parse_string(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words.iter()
+        .flat_map(|w| w.to_le_bytes().to_vec())
+        .take_while(|b| *b != 0)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+impl Module {
+    fn parse(words: &[u32]) -> Result<Self, ReflectionError> {
+        if words.len() < 5 || words[0] != MAGIC_NUMBER {
+            return Err(ReflectionError::InvalidSpirv("bad header"));
+        }
+
+        let mut module = Module::default();
+        let mut i = 5;
+        while i < words.len() {
+            let word_count = (words[i] >> 16) as usize;
+            let opcode = words[i] & 0xffff;
+            if word_count == 0 || i + word_count > words.len() {
+                return Err(ReflectionError::InvalidSpirv("truncated instruction"));
+            }
+            let ops = &words[i + 1 .. i + word_count];
+            match opcode {
+                OP_NAME => { module.names.insert(ops[0], parse_string(&ops[1..])); },
+                OP_MEMBER_NAME => { module.member_names.insert((ops[0], ops[1]), parse_string(&ops[2..])); },
+                // execution model 0 = Vertex
+                OP_ENTRY_POINT => module.is_vertex_shader |= ops[0] == 0,
+                OP_TYPE_BOOL => { module.types.insert(ops[0], Type::Other); },
+                OP_TYPE_INT => {
+                    let kind = if ops[2] == 1 { NumericKind::Int } else { NumericKind::Uint };
+                    module.types.insert(ops[0], Type::Scalar { kind, bits: ops[1] });
+                },
+                OP_TYPE_FLOAT => { module.types.insert(ops[0], Type::Scalar { kind: NumericKind::Float, bits: ops[1] }); },
+                OP_TYPE_VECTOR => { module.types.insert(ops[0], Type::Vector { component: ops[1], count: ops[2] }); },
+                OP_TYPE_MATRIX => { module.types.insert(ops[0], Type::Matrix { column: ops[1], count: ops[2] }); },
+                OP_TYPE_ARRAY => { module.types.insert(ops[0], Type::Array { element: ops[1], length: ops[2] }); },
+                OP_TYPE_RUNTIME_ARRAY => { module.types.insert(ops[0], Type::RuntimeArray { element: ops[1] }); },
+                OP_TYPE_STRUCT => { module.types.insert(ops[0], Type::Struct { members: ops[1..].to_vec() }); },
+                OP_TYPE_POINTER => { module.types.insert(ops[0], Type::Pointer { pointee: ops[2] }); },
+                // NOTE: only the low word is kept; enough for array lengths
+                OP_CONSTANT => { module.constants.insert(ops[1], ops[2]); },
+                OP_VARIABLE => module.variables.push((ops[1], ops[0], ops[2])),
+                OP_DECORATE => {
+                    let value = ops.get(2).cloned().unwrap_or(0);
+                    module.decorations.insert((ops[0], ops[1]), value);
+                },
+                OP_MEMBER_DECORATE => {
+                    let value = ops.get(3).cloned().unwrap_or(0);
+                    module.member_decorations.insert((ops[0], ops[1], ops[2]), value);
+                },
+                _ => ()
+            }
+            i += word_count;
+        }
+
+        Ok(module)
+    }
+
+    fn pointee(&self, pointer_type: u32) -> Option<u32> {
+        match self.types.get(&pointer_type) {
+            Some(Type::Pointer { pointee }) => Some(*pointee),
+            _ => None
+        }
+    }
+
+    fn attribute_format(&self, type_id: u32) -> Option<AttributeFormat> {
+        match self.types.get(&type_id)? {
+            Type::Scalar { kind, bits } => Some(AttributeFormat { kind: *kind, bits: *bits, components: 1 }),
+            Type::Vector { component, count } => {
+                let scalar = self.attribute_format(*component)?;
+                Some(AttributeFormat { components: *count, ..scalar })
+            },
+            _ => None
+        }
+    }
+
+    /// Size of a type inside a uniform block. Uses the strides decorated by the compiler,
+    /// so this reflects the actual (std140) layout.
+    fn size_of(&self, type_id: u32, matrix_stride: Option<u32>) -> u32 {
+        match self.types.get(&type_id) {
+            Some(Type::Scalar { bits, .. }) => bits / 8,
+            Some(Type::Vector { component, count }) => self.size_of(*component, None) * count,
+            Some(Type::Matrix { count, .. }) => matrix_stride.unwrap_or(16) * count,
+            Some(Type::Array { length, .. }) => {
+                let stride = self.decorations.get(&(type_id, DECORATION_ARRAY_STRIDE)).cloned().unwrap_or(0);
+                stride * self.constants.get(length).cloned().unwrap_or(0)
+            },
+            Some(Type::Struct { members }) => {
+                members.iter().enumerate()
+                    .map(|(i, member)| {
+                        let i = i as u32;
+                        let offset = self.member_decorations.get(&(type_id, i, DECORATION_OFFSET)).cloned().unwrap_or(0);
+                        let stride = self.member_decorations.get(&(type_id, i, DECORATION_MATRIX_STRIDE)).cloned();
+                        offset + self.size_of(*member, stride)
+                    })
+                    .max()
+                    .unwrap_or(0)
+            },
+            _ => 0
+        }
+    }
+
+    fn block(&self, type_id: u32, set: u32, binding: u32, size: u32, array_element: bool) -> UniformBlock {
+        let members = match self.types.get(&type_id) {
+            Some(Type::Struct { members }) => members.iter().enumerate()
+                .map(|(i, member)| {
+                    let i = i as u32;
+                    let stride = self.member_decorations.get(&(type_id, i, DECORATION_MATRIX_STRIDE)).cloned();
+                    BlockMember {
+                        name: self.member_names.get(&(type_id, i)).cloned().unwrap_or_default(),
+                        offset: self.member_decorations.get(&(type_id, i, DECORATION_OFFSET)).cloned().unwrap_or(0),
+                        size: self.size_of(*member, stride),
+                    }
+                })
+                .collect(),
+            _ => vec![]
+        };
+        UniformBlock {
+            name: self.names.get(&type_id).cloned().unwrap_or_default(),
+            set,
+            binding,
+            size,
+            members,
+            array_element,
+        }
+    }
+
+    fn interface(&self) -> ShaderInterface {
+        let mut interface = ShaderInterface::default();
+        for &(id, pointer_type, storage_class) in &self.variables {
+            let type_id = match self.pointee(pointer_type) {
+                Some(type_id) => type_id,
+                None => continue
+            };
+
+            if storage_class == STORAGE_CLASS_INPUT && self.is_vertex_shader {
+                if self.decorations.contains_key(&(id, DECORATION_BUILT_IN)) {
+                    continue;
+                }
+                if let Some(location) = self.decorations.get(&(id, DECORATION_LOCATION)) {
+                    interface.vertex_inputs.push(VertexInput {
+                        name: self.names.get(&id).cloned().unwrap_or_default(),
+                        location: *location,
+                        format: self.attribute_format(type_id),
+                    });
+                }
+            } else if storage_class == STORAGE_CLASS_UNIFORM || storage_class == STORAGE_CLASS_STORAGE_BUFFER {
+                let members = match self.types.get(&type_id) {
+                    Some(Type::Struct { members }) => members,
+                    _ => continue
+                };
+                let set = self.decorations.get(&(id, DECORATION_DESCRIPTOR_SET)).cloned().unwrap_or(0);
+                let binding = self.decorations.get(&(id, DECORATION_BINDING)).cloned().unwrap_or(0);
+                interface.uniform_blocks.push(self.block(type_id, set, binding, self.size_of(type_id, None), false));
+
+                // arrays of structs, e.g. one entry per object
+                for &member in members {
+                    let element = match self.types.get(&member) {
+                        Some(Type::Array { element, .. }) | Some(Type::RuntimeArray { element }) => *element,
+                        _ => continue
+                    };
+                    if let Some(Type::Struct { .. }) = self.types.get(&element) {
+                        let stride = self.decorations.get(&(member, DECORATION_ARRAY_STRIDE)).cloned().unwrap_or(0);
+                        interface.uniform_blocks.push(self.block(element, set, binding, stride, true));
+                    }
+                }
+            }
+        }
+        interface.vertex_inputs.sort_by_key(|input| input.location);
+        interface
+    }
+}
+
+/// Reads the vertex inputs and uniform blocks of a SPIR-V module.
+pub fn reflect(words: &[u32]) -> Result<ShaderInterface, ReflectionError> {
+    Ok(Module::parse(words)?.interface())
+}
+
+fn rust_attribute_format<V: Vertex>(name: &str) -> Option<AttributeFormat> {
+    let info = V::member(name)?;
+    let (kind, bits) = match info.ty {
+        VertexMemberTy::I8 => (NumericKind::Int, 8),
+        VertexMemberTy::U8 => (NumericKind::Uint, 8),
+        VertexMemberTy::I16 => (NumericKind::Int, 16),
+        VertexMemberTy::U16 => (NumericKind::Uint, 16),
+        VertexMemberTy::I32 => (NumericKind::Int, 32),
+        VertexMemberTy::U32 => (NumericKind::Uint, 32),
+        VertexMemberTy::F32 => (NumericKind::Float, 32),
+        VertexMemberTy::F64 => (NumericKind::Float, 64),
+    };
+    Some(AttributeFormat { kind, bits, components: info.array_size as u32 })
+}
+
+/// Checks the vertex inputs of a shader against a Rust vertex type.
+/// `fields` are the field names as passed to `impl_vertex!`, their index is the expected location.
+pub fn check_vertex_input<V: Vertex>(interface: &ShaderInterface, fields: &[&str]) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+
+    for input in &interface.vertex_inputs {
+        let location = match fields.iter().position(|field| *field == input.name) {
+            Some(location) => location as u32,
+            None => {
+                mismatches.push(Mismatch::MissingVertexAttribute { name: input.name.clone() });
+                continue;
+            }
+        };
+        if location != input.location {
+            mismatches.push(Mismatch::VertexLocation {
+                name: input.name.clone(), shader: input.location, rust: location });
+        }
+
+        let rust_format = rust_attribute_format::<V>(&input.name);
+        if input.format.is_none() || rust_format != input.format {
+            let describe = |format: Option<AttributeFormat>|
+                format.map(|f| f.to_string()).unwrap_or_else(|| "unsupported".into());
+            mismatches.push(Mismatch::VertexFormat {
+                name: input.name.clone(), shader: describe(input.format), rust: describe(rust_format) });
+        }
+    }
+
+    for field in fields {
+        if !interface.vertex_inputs.iter().any(|input| input.name == *field) {
+            mismatches.push(Mismatch::UnusedVertexAttribute { name: field.to_string() });
+        }
+    }
+
+    mismatches
+}
+
+/// Checks a uniform block (by type name) against the layout of a Rust struct.
+pub fn check_uniform_block(
+    interface: &ShaderInterface,
+    block_name: &str,
+    rust_members: &[RustMember],
+    rust_size: usize,
+) -> Vec<Mismatch> {
+    let block = match interface.uniform_blocks.iter().find(|b| b.name == block_name) {
+        Some(block) => block,
+        None => return vec![Mismatch::MissingUniformBlock { name: block_name.into() }]
+    };
+
+    let mut mismatches = Vec::new();
+    for member in &block.members {
+        let rust_member = match rust_members.iter().find(|m| m.name == member.name) {
+            Some(rust_member) => rust_member,
+            None => {
+                mismatches.push(Mismatch::MissingBlockMember { block: block.name.clone(), name: member.name.clone() });
+                continue;
+            }
+        };
+        if member.offset as usize != rust_member.offset {
+            mismatches.push(Mismatch::MemberOffset {
+                block: block.name.clone(), name: member.name.clone(),
+                shader: member.offset, rust: rust_member.offset });
+        }
+        if member.size as usize != rust_member.size {
+            mismatches.push(Mismatch::MemberSize {
+                block: block.name.clone(), name: member.name.clone(),
+                shader: member.size, rust: rust_member.size });
+        }
+    }
+
+    for rust_member in rust_members {
+        if !block.members.iter().any(|m| m.name == rust_member.name) {
+            mismatches.push(Mismatch::ExtraBlockMember { block: block.name.clone(), name: rust_member.name.into() });
+        }
+    }
+
+    // NOTE: the Rust struct may be larger due to trailing padding, but not smaller.
+    // Array elements need to be exactly as large as the array stride, though.
+    if rust_size < block.size as usize || (block.array_element && rust_size != block.size as usize) {
+        mismatches.push(Mismatch::BlockSize { block: block.name.clone(), shader: block.size, rust: rust_size });
+    }
+
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Copy, Clone)]
+    struct TestVertex {
+        pos: [f32; 2],
+        color: [f32; 3],
+    }
+    impl_vertex!(TestVertex, pos, color);
+
+    /// Matches the std140 layout of `TestUniforms` in `VERTEX_SHADER`.
+    #[repr(C)]
+    struct TestUniforms {
+        scale: f32,
+        _padding: [f32; 3],
+        offset: [f32; 4],
+    }
+
+    /// Without the padding that std140 adds before the `vec4`.
+    #[repr(C)]
+    struct PackedUniforms {
+        scale: f32,
+        offset: [f32; 4],
+    }
+
+    const VERTEX_SHADER: &str = "
+        #version 450
+        layout(binding = 0) uniform TestUniforms {
+            float scale;
+            vec4 offset;
+        } uniforms;
+        layout(location = 0) in vec2 pos;
+        layout(location = 1) in vec3 color;
+        layout(location = 0) out vec3 fragColor;
+        void main() {
+            gl_Position = uniforms.offset + vec4(pos * uniforms.scale, 0.0, 1.0);
+            fragColor = color;
+        }
+    ";
+
+    /// An array of `TestUniforms` (std430), one per instance.
+    const STORAGE_BUFFER_SHADER: &str = "
+        #version 450
+        struct TestUniforms {
+            float scale;
+            vec4 offset;
+        };
+        layout(binding = 0) readonly buffer TestBuffer {
+            TestUniforms entries[];
+        };
+        layout(location = 0) in vec2 pos;
+        void main() {
+            TestUniforms uniforms = entries[gl_InstanceIndex];
+            gl_Position = uniforms.offset + vec4(pos * uniforms.scale, 0.0, 1.0);
+        }
+    ";
+
+    fn reflect_vertex_shader(source: &str) -> ShaderInterface {
+        let words = compile_glsl_source(source, shaderc::ShaderKind::Vertex, "test.vert").unwrap();
+        reflect(&words).unwrap()
+    }
+
+    #[test]
+    fn matching_interface() {
+        let interface = reflect_vertex_shader(VERTEX_SHADER);
+        assert_eq!(check_vertex_input::<TestVertex>(&interface, &["pos", "color"]), vec![]);
+        let layout = rust_layout!(TestUniforms { scale, offset });
+        assert_eq!(check_uniform_block(&interface, "TestUniforms", &layout, ::std::mem::size_of::<TestUniforms>()),
+            vec![]);
+    }
+
+    #[test]
+    fn wrong_location() {
+        let source = VERTEX_SHADER
+            .replace("location = 0) in vec2 pos", "location = 1) in vec2 pos")
+            .replace("location = 1) in vec3 color", "location = 0) in vec3 color");
+        let interface = reflect_vertex_shader(&source);
+        let mismatches = check_vertex_input::<TestVertex>(&interface, &["pos", "color"]);
+        assert!(mismatches.contains(&Mismatch::VertexLocation { name: "pos".into(), shader: 1, rust: 0 }));
+        assert!(mismatches.contains(&Mismatch::VertexLocation { name: "color".into(), shader: 0, rust: 1 }));
+        assert_eq!(mismatches.len(), 2);
+    }
+
+    #[test]
+    fn wrong_format() {
+        let source = VERTEX_SHADER
+            .replace("in vec2 pos", "in vec3 pos")
+            .replace("pos * uniforms.scale, 0.0", "pos * uniforms.scale");
+        let interface = reflect_vertex_shader(&source);
+        assert_eq!(check_vertex_input::<TestVertex>(&interface, &["pos", "color"]), vec![
+            Mismatch::VertexFormat { name: "pos".into(), shader: "f32x3".into(), rust: "f32x2".into() },
+        ]);
+    }
+
+    #[test]
+    fn wrong_std140_offset() {
+        let interface = reflect_vertex_shader(VERTEX_SHADER);
+        let layout = rust_layout!(PackedUniforms { scale, offset });
+        let mismatches = check_uniform_block(&interface, "TestUniforms", &layout,
+            ::std::mem::size_of::<PackedUniforms>());
+        assert!(mismatches.contains(&Mismatch::MemberOffset {
+            block: "TestUniforms".into(), name: "offset".into(), shader: 16, rust: 4 }));
+        assert!(mismatches.contains(&Mismatch::BlockSize { block: "TestUniforms".into(), shader: 32, rust: 20 }));
+    }
+
+    #[test]
+    fn array_element_size() {
+        let interface = reflect_vertex_shader(STORAGE_BUFFER_SHADER);
+        let layout = rust_layout!(TestUniforms { scale, offset });
+        assert_eq!(check_uniform_block(&interface, "TestUniforms", &layout, ::std::mem::size_of::<TestUniforms>()),
+            vec![]);
+
+        // trailing padding is fine in a block, but not in an array element
+        #[repr(C)]
+        struct PaddedUniforms {
+            scale: f32,
+            _padding: [f32; 3],
+            offset: [f32; 4],
+            _more_padding: [f32; 4],
+        }
+        let layout = rust_layout!(PaddedUniforms { scale, offset });
+        assert_eq!(check_uniform_block(&interface, "TestUniforms", &layout, ::std::mem::size_of::<PaddedUniforms>()),
+            vec![Mismatch::BlockSize { block: "TestUniforms".into(), shader: 32, rust: 48 }]);
+    }
+}
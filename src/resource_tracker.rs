@@ -0,0 +1,64 @@
+//! Leak check for debug builds: remembers weak references to the app's Vulkan objects before
+//! shutdown and counts how many are still alive after everything was dropped.
+use std::fmt;
+use std::sync::{Arc, Weak};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    Buffer,
+    Image,
+    Pipeline,
+    DescriptorSet,
+}
+
+#[derive(Default)]
+pub struct ResourceTracker {
+    resources: Vec<(ResourceKind, Box<Fn() -> bool>)>,
+}
+
+impl ResourceTracker {
+    pub fn track<T: ?Sized + 'static>(&mut self, kind: ResourceKind, resource: &Arc<T>) {
+        let weak: Weak<T> = Arc::downgrade(resource);
+        self.resources.push((kind, Box::new(move || weak.upgrade().is_some())));
+    }
+
+    pub fn live_counts(&self) -> ResourceCounts {
+        let mut counts = ResourceCounts::default();
+        for &(kind, ref is_alive) in &self.resources {
+            if is_alive() {
+                counts.add(kind);
+            }
+        }
+        counts
+    }
+}
+
+#[derive(Default)]
+pub struct ResourceCounts {
+    buffers: usize,
+    images: usize,
+    pipelines: usize,
+    descriptor_sets: usize,
+}
+
+impl ResourceCounts {
+    fn add(&mut self, kind: ResourceKind) {
+        match kind {
+            ResourceKind::Buffer => self.buffers += 1,
+            ResourceKind::Image => self.images += 1,
+            ResourceKind::Pipeline => self.pipelines += 1,
+            ResourceKind::DescriptorSet => self.descriptor_sets += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.buffers + self.images + self.pipelines + self.descriptor_sets
+    }
+}
+
+impl fmt::Display for ResourceCounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} buffers, {} images, {} pipelines, {} descriptor sets",
+            self.buffers, self.images, self.pipelines, self.descriptor_sets)
+    }
+}
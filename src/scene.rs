@@ -0,0 +1,119 @@
+//! The objects to draw. Each object is one of the meshes with its own transform and tint.
+//! How that per-object data gets to the shader depends on the `DrawMode`.
+use cgmath::{Matrix4, Vector3, Rad, Deg};
+
+use config::{SceneConfig, SceneKind};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DrawMode {
+    /// One draw call per object, per-object data in push constants,
+    /// so no buffer or descriptor set is needed per object.
+    PushConstants,
+    /// One draw call per object, per-object data in an array in a single storage buffer,
+    /// indexed by a push constant (see `object_uniforms`).
+    StorageBuffer,
+    /// One instanced draw call, per-instance data in a second vertex buffer (`objects` is ignored).
+    Instanced,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SceneObject {
+    /// Index into the uploaded meshes (wraps around).
+    pub mesh: usize,
+    pub model: Matrix4<f32>,
+    pub tint: [f32; 4],
+}
+
+pub struct Scene {
+    pub objects: Vec<SceneObject>,
+    pub draw_mode: DrawMode,
+    /// Only used with `DrawMode::Instanced` (see `InstanceData`).
+    pub instance_count: usize,
+}
+
+impl Scene {
+    /// The single quad of the tutorial.
+    pub fn single_quad() -> Self {
+        Self {
+            objects: vec![SceneObject { mesh: 0, model: Matrix4::from_scale(1.0), tint: [1.0, 1.0, 1.0, 1.0] }],
+            draw_mode: DrawMode::PushConstants,
+            instance_count: 0,
+        }
+    }
+
+    /// `count` objects on a grid, cycling through the meshes, each rotated and tinted differently.
+    pub fn quads(count: usize) -> Self {
+        let columns = (count as f32).sqrt().ceil().max(1.0) as usize;
+        let spacing = 1.2;
+        let center = (columns - 1) as f32 * spacing / 2.0;
+        let scale = 1.0 / columns as f32;
+
+        let objects = (0..count)
+            .map(|i| {
+                let x = (i % columns) as f32 * spacing - center;
+                let y = (i / columns) as f32 * spacing - center;
+                let model = Matrix4::from_scale(scale)
+                    * Matrix4::from_translation(Vector3::new(x, y, 0.0))
+                    * Matrix4::from_angle_z(Rad::from(Deg(i as f32 * 15.0)));
+                SceneObject { mesh: i, model, tint: tint(i, count) }
+            })
+            .collect();
+
+        Self { objects, draw_mode: DrawMode::PushConstants, instance_count: 0 }
+    }
+
+    /// Like `quads`, but the per-object data lives in one storage buffer.
+    pub fn storage_buffer_objects(count: usize) -> Self {
+        Self { draw_mode: DrawMode::StorageBuffer, ..Self::quads(count) }
+    }
+
+    pub fn instanced(instance_count: usize) -> Self {
+        Self { objects: vec![], draw_mode: DrawMode::Instanced, instance_count }
+    }
+
+    /// `scene` in the config (`--quads [N]`, `--objects [N]` or `--instances [N]`).
+    pub fn from_config(config: &SceneConfig) -> Self {
+        match config.kind {
+            SceneKind::SingleQuad => Self::single_quad(),
+            SceneKind::Quads => Self::quads(config.count()),
+            SceneKind::Objects => Self::storage_buffer_objects(config.count()),
+            SceneKind::Instances => Self::instanced(config.count()),
+        }
+    }
+}
+
+/// Model matrix of an object at the given time; each object spins at its own speed.
+pub fn animate_object(index: usize, object: &SceneObject, seconds: f32) -> Matrix4<f32> {
+    let speed = 30.0 * (1 + index % 5) as f32;
+    object.model * Matrix4::from_angle_z(Rad::from(Deg(seconds * speed)))
+}
+
+/// Lays out `count` instances on a grid in the same area the single quad covers and animates
+/// them. Returns (offset, scale, color) per instance.
+pub fn animate_instances(count: usize, seconds: f32) -> impl Iterator<Item=([f32; 2], f32, [f32; 3])> {
+    let columns = (count as f32).sqrt().ceil().max(1.0) as usize;
+    let cell = 1.0 / columns as f32;
+
+    (0..count).map(move |i| {
+        let column = (i % columns) as f32;
+        let row = (i / columns) as f32;
+        let phase = seconds * 2.0 + (column + row) * 0.3;
+        let offset = [
+            -0.5 + (column + 0.5) * cell,
+            -0.5 + (row + 0.5) * cell + phase.sin() * cell * 0.25,
+        ];
+        let scale = cell * (0.6 + 0.3 * (phase * 0.5).cos());
+        let t = tint(i, count);
+        (offset, scale, [t[0], t[1], t[2]])
+    })
+}
+
+/// Spreads the hue over all objects.
+fn tint(i: usize, count: usize) -> [f32; 4] {
+    let hue = i as f32 / count as f32 * 6.0;
+    let channel = |offset: f32| {
+        let h = (hue + offset) % 6.0;
+        (2.0 - (h - 3.0).abs()).max(0.0).min(1.0)
+    };
+    [channel(0.0), channel(2.0), channel(4.0), 1.0]
+}
@@ -0,0 +1,32 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(binding = 0) uniform UniformBufferObject {
+    mat4 model;
+    mat4 view;
+    mat4 proj;
+} ubo;
+
+// NOTE: same constant as in shader.vert, so both pipelines can share `ShaderSpecialization`
+layout(constant_id = 0) const bool use_vertex_color = true;
+
+// NOTE: names must match the `Vertex` struct in Rust
+layout(location = 0) in vec2 pos;
+layout(location = 1) in vec3 color;
+
+// NOTE: per-instance, names must match the `InstanceData` struct in Rust
+layout(location = 2) in vec2 instance_offset;
+layout(location = 3) in float instance_scale;
+layout(location = 4) in vec3 instance_color;
+
+layout(location = 0) out vec3 fragColor;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+
+void main() {
+    vec2 position = pos * instance_scale + instance_offset;
+    gl_Position = ubo.proj * ubo.view * ubo.model * vec4(position, 0.0, 1.0);
+    fragColor = (use_vertex_color ? color : vec3(1.0)) * instance_color;
+}
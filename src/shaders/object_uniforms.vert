@@ -0,0 +1,43 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(set = 0, binding = 0) uniform UniformBufferObject {
+    mat4 model;
+    mat4 view;
+    mat4 proj;
+} ubo;
+
+// NOTE: mirrored by `ObjectUniforms` in Rust
+struct ObjectUniforms {
+    mat4 model;
+    vec4 tint;
+};
+
+// NOTE: one entry per object in a single buffer, see `ObjectUniformBuffer` in Rust
+layout(set = 1, binding = 0) readonly buffer ObjectBuffer {
+    ObjectUniforms objects[];
+};
+
+// NOTE: selects the entry of the object in each draw call (instead of a dynamic offset)
+layout(push_constant) uniform PushConstants {
+    uint object_index;
+} push;
+
+// NOTE: same constant as in shader.vert, so all pipelines can share `ShaderSpecialization`
+layout(constant_id = 0) const bool use_vertex_color = true;
+
+// NOTE: names must match the `Vertex` struct in Rust
+layout(location = 0) in vec2 pos;
+layout(location = 1) in vec3 color;
+
+layout(location = 0) out vec3 fragColor;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+
+void main() {
+    ObjectUniforms object = objects[push.object_index];
+    gl_Position = ubo.proj * ubo.view * ubo.model * object.model * vec4(pos, 0.0, 1.0);
+    fragColor = (use_vertex_color ? color : vec3(1.0)) * object.tint.rgb;
+}
@@ -1,10 +1,17 @@
 #version 450
 #extension GL_ARB_separate_shader_objects : enable
 
+// NOTE: set from Rust with `fragment_shader::SpecializationConstants`; 0 = no posterization
+layout(constant_id = 0) const int color_levels = 0;
+
 layout(location = 0) in vec3 fragColor;
 
 layout(location = 0) out vec4 outColor;
 
 void main() {
-    outColor = vec4(fragColor, 1.0);
+    vec3 color = fragColor;
+    if (color_levels > 0) {
+        color = floor(color * float(color_levels)) / float(color_levels);
+    }
+    outColor = vec4(color, 1.0);
 }
@@ -1,6 +1,21 @@
 #version 450
 #extension GL_ARB_separate_shader_objects : enable
 
+layout(binding = 0) uniform UniformBufferObject {
+    mat4 model;
+    mat4 view;
+    mat4 proj;
+} ubo;
+
+// NOTE: set from Rust with `vertex_shader::SpecializationConstants`; false = flat color (just the tint)
+layout(constant_id = 0) const bool use_vertex_color = true;
+
+// NOTE: per-draw data; mirrored by `vertex_shader::ty::PushConstants` in Rust (generated by vulkano_shaders)
+layout(push_constant) uniform PushConstants {
+    mat4 model;
+    vec4 tint;
+} push;
+
 // NOTE: names must match the `Vertex` struct in Rust
 layout(location = 0) in vec2 pos;
 layout(location = 1) in vec3 color;
@@ -12,6 +27,6 @@ out gl_PerVertex {
 };
 
 void main() {
-    gl_Position = vec4(pos, 0.0, 1.0);
-    fragColor = color;
+    gl_Position = ubo.proj * ubo.view * ubo.model * push.model * vec4(pos, 0.0, 1.0);
+    fragColor = (use_vertex_color ? color : vec3(1.0)) * push.tint.rgb;
 }
@@ -0,0 +1,81 @@
+//! Transient per-frame uploads.
+//!
+//! Instead of writing into host-visible buffers that the GPU might still be reading (and
+//! skipping the update if it is), each frame's data is written into a fresh sub-buffer of a
+//! `CpuBufferPool` and copied into the per-image device-local buffers at the start of the frame.
+//! The pool hands out space linearly and reuses it once a sub-buffer is dropped, which happens
+//! when the future of the frame that used it is cleaned up (i.e. after its fence signaled).
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::cpu_pool::{CpuBufferPool, CpuBufferPoolChunk, CpuBufferPoolSubbuffer};
+use vulkano::device::Device;
+use vulkano::memory::DeviceMemoryAllocError;
+use vulkano::memory::pool::StdMemoryPool;
+
+pub type Subbuffer<T> = CpuBufferPoolSubbuffer<T, Arc<StdMemoryPool>>;
+pub type Chunk<T> = CpuBufferPoolChunk<T, Arc<StdMemoryPool>>;
+
+/// A pool of transfer source buffers for one type of per-frame data.
+pub struct UploadRing<T> {
+    pool: CpuBufferPool<T>,
+}
+
+impl<T> UploadRing<T> where T: Send + Sync + 'static {
+    pub fn new(device: &Arc<Device>) -> Self {
+        Self { pool: CpuBufferPool::new(device.clone(), BufferUsage::transfer_source()) }
+    }
+
+    pub fn upload(&self, data: T, stats: &mut UploadStats) -> Result<Subbuffer<T>, DeviceMemoryAllocError> {
+        let subbuffer = self.pool.next(data)?;
+        stats.add(mem::size_of::<T>());
+        Ok(subbuffer)
+    }
+
+    pub fn upload_iter<I>(&self, data: I, stats: &mut UploadStats) -> Result<Chunk<T>, DeviceMemoryAllocError>
+        where I: IntoIterator<Item=T>, I::IntoIter: ExactSizeIterator
+    {
+        let data = data.into_iter();
+        let bytes = data.len() * mem::size_of::<T>();
+        let chunk = self.pool.chunk(data)?;
+        stats.add(bytes);
+        Ok(chunk)
+    }
+}
+
+/// Bytes uploaded through the rings, per frame.
+#[derive(Debug, Default)]
+pub struct UploadStats {
+    current_frame_bytes: usize,
+    pub frames: usize,
+    pub total_bytes: usize,
+    pub last_frame_bytes: usize,
+    pub max_frame_bytes: usize,
+}
+
+impl UploadStats {
+    fn add(&mut self, bytes: usize) {
+        self.current_frame_bytes += bytes;
+    }
+
+    pub fn end_frame(&mut self) {
+        self.frames += 1;
+        self.total_bytes += self.current_frame_bytes;
+        self.last_frame_bytes = self.current_frame_bytes;
+        self.max_frame_bytes = self.max_frame_bytes.max(self.current_frame_bytes);
+        self.current_frame_bytes = 0;
+    }
+
+    pub fn average_frame_bytes(&self) -> usize {
+        if self.frames == 0 { 0 } else { self.total_bytes / self.frames }
+    }
+}
+
+impl fmt::Display for UploadStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} frames, {} bytes uploaded per frame (last {}, max {}), {} bytes total",
+            self.frames, self.average_frame_bytes(), self.last_frame_bytes, self.max_frame_bytes, self.total_bytes)
+    }
+}